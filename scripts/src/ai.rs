@@ -0,0 +1,1062 @@
+use gdnative::prelude::*;
+use rand::{Rng, RngCore};
+
+use crate::utils::normalized;
+
+// What the tree reads about the world and what it wants the body to do.
+// The enemy fills the inputs before every tick and applies `action` after it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Blackboard {
+    pub position: Vector2,
+    // Where the enemy started, centre of wandering and patrols
+    pub home: Vector2,
+    // Position of the current target, `None` without one
+    pub target: Option<Vector2>,
+    // Where a lost target was last seen, while the enemy still looks for it
+    pub last_known: Option<Vector2>,
+    // `health / max_health`
+    pub health_ratio: f32,
+    // Seconds since the tree started, and since the last tick
+    pub time: f32,
+    pub delta: f32,
+
+    pub action: AiAction,
+}
+
+impl Default for Blackboard {
+    fn default() -> Self {
+        Blackboard {
+            position: Vector2::zero(),
+            home: Vector2::zero(),
+            target: None,
+            last_known: None,
+            health_ratio: 1.0,
+            time: 0.0,
+            delta: 0.0,
+
+            action: AiAction::Stop,
+        }
+    }
+}
+
+impl Blackboard {
+    pub fn distance_to(&self, point: Vector2) -> f32 {
+        (point - self.position).length()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AiAction {
+    Stop,
+    MoveTo(Vector2),
+    MoveAway(Vector2),
+    // Stand still and hit whatever is at the point
+    Attack(Vector2),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Status {
+    Success,
+    Failure,
+    Running,
+}
+
+// A node of the behaviour tree.
+pub trait Behavior {
+    fn tick(&mut self, bb: &mut Blackboard, rng: &mut dyn RngCore) -> Status;
+
+    // Forgets progress, called when the parent stops ticking this node while it runs
+    fn reset(&mut self) {}
+}
+
+pub type BoxedBehavior = Box<dyn Behavior>;
+
+// Ticks its children in order until one does not fail.
+// Re-evaluated from the first child every tick, so higher priorities interrupt lower ones.
+pub struct Selector {
+    children: Vec<BoxedBehavior>,
+    running: Option<usize>,
+}
+
+impl Selector {
+    pub fn new(children: Vec<BoxedBehavior>) -> Self {
+        Selector {
+            children,
+            running: None,
+        }
+    }
+}
+
+impl Behavior for Selector {
+    fn tick(&mut self, bb: &mut Blackboard, rng: &mut dyn RngCore) -> Status {
+        for index in 0..self.children.len() {
+            let status = self.children[index].tick(bb, rng);
+
+            if status != Status::Failure {
+                switch_running(&mut self.children, &mut self.running, index, status);
+                return status;
+            }
+        }
+
+        self.reset();
+        Status::Failure
+    }
+
+    fn reset(&mut self) {
+        if let Some(index) = self.running.take() {
+            self.children[index].reset();
+        }
+    }
+}
+
+// Ticks its children in order until one does not succeed.
+// Conditions first, then actions, e.g. `[health_below(0.3), Flee]`.
+pub struct Sequence {
+    children: Vec<BoxedBehavior>,
+    running: Option<usize>,
+}
+
+impl Sequence {
+    pub fn new(children: Vec<BoxedBehavior>) -> Self {
+        Sequence {
+            children,
+            running: None,
+        }
+    }
+}
+
+impl Behavior for Sequence {
+    fn tick(&mut self, bb: &mut Blackboard, rng: &mut dyn RngCore) -> Status {
+        for index in 0..self.children.len() {
+            let status = self.children[index].tick(bb, rng);
+
+            if status != Status::Success {
+                switch_running(&mut self.children, &mut self.running, index, status);
+                return status;
+            }
+        }
+
+        self.reset();
+        Status::Success
+    }
+
+    fn reset(&mut self) {
+        if let Some(index) = self.running.take() {
+            self.children[index].reset();
+        }
+    }
+}
+
+// Remembers which child is running and resets the one it replaces
+fn switch_running(
+    children: &mut [BoxedBehavior],
+    running: &mut Option<usize>,
+    index: usize,
+    status: Status,
+) {
+    if let Some(previous) = *running {
+        if previous != index {
+            children[previous].reset();
+        }
+    }
+
+    *running = if status == Status::Running {
+        Some(index)
+    } else {
+        None
+    };
+}
+
+// Picks one child at random and ticks it until it finishes.
+pub struct RandomSelector {
+    children: Vec<BoxedBehavior>,
+    current: Option<usize>,
+}
+
+impl RandomSelector {
+    pub fn new(children: Vec<BoxedBehavior>) -> Self {
+        RandomSelector {
+            children,
+            current: None,
+        }
+    }
+}
+
+impl Behavior for RandomSelector {
+    fn tick(&mut self, bb: &mut Blackboard, rng: &mut dyn RngCore) -> Status {
+        if self.children.is_empty() {
+            return Status::Failure;
+        }
+
+        let len = self.children.len();
+        let index = *self.current.get_or_insert_with(|| rng.gen_range(0..len));
+
+        let status = self.children[index].tick(bb, rng);
+        if status != Status::Running {
+            self.current = None;
+        }
+
+        status
+    }
+
+    fn reset(&mut self) {
+        if let Some(index) = self.current.take() {
+            self.children[index].reset();
+        }
+    }
+}
+
+// Succeeds when the check holds, fails otherwise.
+pub struct Condition {
+    check: Box<dyn Fn(&Blackboard) -> bool>,
+}
+
+impl Condition {
+    pub fn new(check: impl Fn(&Blackboard) -> bool + 'static) -> Self {
+        Condition {
+            check: Box::new(check),
+        }
+    }
+
+    pub fn health_below(ratio: f32) -> Self {
+        Condition::new(move |bb| bb.health_ratio < ratio)
+    }
+}
+
+impl Behavior for Condition {
+    fn tick(&mut self, bb: &mut Blackboard, _rng: &mut dyn RngCore) -> Status {
+        if (self.check)(bb) {
+            Status::Success
+        } else {
+            Status::Failure
+        }
+    }
+}
+
+// Stands still for a random time between `min_time` and `max_time`.
+pub struct Idle {
+    min_time: f32,
+    max_time: f32,
+    remaining: Option<f32>,
+}
+
+impl Idle {
+    pub fn new(min_time: f32, max_time: f32) -> Self {
+        Idle {
+            min_time,
+            max_time,
+            remaining: None,
+        }
+    }
+}
+
+impl Behavior for Idle {
+    fn tick(&mut self, bb: &mut Blackboard, rng: &mut dyn RngCore) -> Status {
+        let (min_time, max_time) = (self.min_time, self.max_time);
+        let remaining = self
+            .remaining
+            .get_or_insert_with(|| random_time(rng, min_time, max_time));
+
+        bb.action = AiAction::Stop;
+        *remaining -= bb.delta;
+
+        if *remaining <= 0.0 {
+            self.remaining = None;
+            return Status::Success;
+        }
+
+        Status::Running
+    }
+
+    fn reset(&mut self) {
+        self.remaining = None;
+    }
+}
+
+// Walks to a random point up to `range` away from home on each axis.
+// Succeeds on arrival, or when it took longer than a random time between `min_time` and `max_time`.
+pub struct Wander {
+    range: f32,
+    arrive_distance: f32,
+    min_time: f32,
+    max_time: f32,
+    target: Option<(Vector2, f32)>,
+}
+
+impl Wander {
+    pub fn new(range: f32, arrive_distance: f32, min_time: f32, max_time: f32) -> Self {
+        Wander {
+            range,
+            arrive_distance,
+            min_time,
+            max_time,
+            target: None,
+        }
+    }
+}
+
+impl Behavior for Wander {
+    fn tick(&mut self, bb: &mut Blackboard, rng: &mut dyn RngCore) -> Status {
+        let (range, min_time, max_time) = (self.range, self.min_time, self.max_time);
+        let (point, remaining) = self.target.get_or_insert_with(|| {
+            let offset = Vector2::new(rng.gen_range(-range..=range), rng.gen_range(-range..=range));
+            (bb.home + offset, random_time(rng, min_time, max_time))
+        });
+
+        *remaining -= bb.delta;
+
+        if bb.distance_to(*point) <= self.arrive_distance || *remaining <= 0.0 {
+            self.target = None;
+            bb.action = AiAction::Stop;
+            return Status::Success;
+        }
+
+        bb.action = AiAction::MoveTo(*point);
+        Status::Running
+    }
+
+    fn reset(&mut self) {
+        self.target = None;
+    }
+}
+
+// Moves towards the target for as long as there is one.
+pub struct Chase;
+
+impl Behavior for Chase {
+    fn tick(&mut self, bb: &mut Blackboard, _rng: &mut dyn RngCore) -> Status {
+        match bb.target {
+            Some(target) => {
+                bb.action = AiAction::MoveTo(target);
+                Status::Running
+            }
+            None => Status::Failure,
+        }
+    }
+}
+
+// Goes to where the target was last seen, then looks around within `radius` of it.
+// Runs for as long as the enemy remembers the spot.
+pub struct Search {
+    radius: f32,
+    arrive_distance: f32,
+    point: Option<Vector2>,
+}
+
+impl Search {
+    pub fn new(radius: f32, arrive_distance: f32) -> Self {
+        Search {
+            radius,
+            arrive_distance,
+            point: None,
+        }
+    }
+}
+
+impl Behavior for Search {
+    fn tick(&mut self, bb: &mut Blackboard, rng: &mut dyn RngCore) -> Status {
+        let last_known = match bb.last_known {
+            Some(last_known) => last_known,
+            None => {
+                self.point = None;
+                return Status::Failure;
+            }
+        };
+
+        let mut point = *self.point.get_or_insert(last_known);
+        if bb.distance_to(point) <= self.arrive_distance {
+            point = last_known
+                + Vector2::new(
+                    rng.gen_range(-self.radius..=self.radius),
+                    rng.gen_range(-self.radius..=self.radius),
+                );
+            self.point = Some(point);
+        }
+
+        bb.action = AiAction::MoveTo(point);
+        Status::Running
+    }
+
+    fn reset(&mut self) {
+        self.point = None;
+    }
+}
+
+// Runs from the target until it is `safe_distance` away.
+pub struct Flee {
+    safe_distance: f32,
+}
+
+impl Flee {
+    pub fn new(safe_distance: f32) -> Self {
+        Flee { safe_distance }
+    }
+}
+
+impl Behavior for Flee {
+    fn tick(&mut self, bb: &mut Blackboard, _rng: &mut dyn RngCore) -> Status {
+        let target = match bb.target {
+            Some(target) => target,
+            None => return Status::Failure,
+        };
+
+        if bb.distance_to(target) >= self.safe_distance {
+            return Status::Success;
+        }
+
+        bb.action = AiAction::MoveAway(target);
+        Status::Running
+    }
+}
+
+// Hits the target when it is within `range`, at most once every `cooldown` seconds.
+// Holds still while waiting for the cooldown.
+pub struct Attack {
+    range: f32,
+    cooldown: f32,
+    ready_at: f32,
+}
+
+impl Attack {
+    pub fn new(range: f32, cooldown: f32) -> Self {
+        Attack {
+            range,
+            cooldown,
+            ready_at: 0.0,
+        }
+    }
+}
+
+impl Behavior for Attack {
+    fn tick(&mut self, bb: &mut Blackboard, _rng: &mut dyn RngCore) -> Status {
+        let target = match bb.target {
+            Some(target) if bb.distance_to(target) <= self.range => target,
+            _ => return Status::Failure,
+        };
+
+        if bb.time < self.ready_at {
+            bb.action = AiAction::Stop;
+            return Status::Running;
+        }
+
+        self.ready_at = bb.time + self.cooldown;
+        bb.action = AiAction::Attack(target);
+        Status::Success
+    }
+}
+
+// Walks through `points`, given relative to home, looping back to the first one.
+// Succeeds each time a point is reached, so a sequence can pause there.
+pub struct Patrol {
+    points: Vec<Vector2>,
+    arrive_distance: f32,
+    index: usize,
+}
+
+impl Patrol {
+    pub fn new(points: Vec<Vector2>, arrive_distance: f32) -> Self {
+        Patrol {
+            points,
+            arrive_distance,
+            index: 0,
+        }
+    }
+}
+
+impl Behavior for Patrol {
+    fn tick(&mut self, bb: &mut Blackboard, _rng: &mut dyn RngCore) -> Status {
+        let point = match self.points.get(self.index) {
+            Some(point) => bb.home + *point,
+            None => return Status::Failure,
+        };
+
+        if bb.distance_to(point) <= self.arrive_distance {
+            self.index = (self.index + 1) % self.points.len();
+            bb.action = AiAction::Stop;
+            return Status::Success;
+        }
+
+        bb.action = AiAction::MoveTo(point);
+        Status::Running
+    }
+}
+
+// Walks back to where the enemy started once it is more than `leash` away,
+// and keeps going until it is home rather than turning back at the leash.
+// Fails while the enemy is within its leash.
+pub struct ReturnHome {
+    leash: f32,
+    arrive_distance: f32,
+    returning: bool,
+}
+
+impl ReturnHome {
+    pub fn new(leash: f32, arrive_distance: f32) -> Self {
+        ReturnHome {
+            leash,
+            arrive_distance,
+            returning: false,
+        }
+    }
+}
+
+impl Behavior for ReturnHome {
+    fn tick(&mut self, bb: &mut Blackboard, _rng: &mut dyn RngCore) -> Status {
+        let distance = bb.distance_to(bb.home);
+
+        if !self.returning {
+            if distance <= self.leash {
+                return Status::Failure;
+            }
+            self.returning = true;
+        }
+
+        if distance <= self.arrive_distance {
+            self.returning = false;
+            bb.action = AiAction::Stop;
+            return Status::Success;
+        }
+
+        bb.action = AiAction::MoveTo(bb.home);
+        Status::Running
+    }
+
+    fn reset(&mut self) {
+        self.returning = false;
+    }
+}
+
+// Settings of the standard enemy tree, by priority:
+// flee when hurt > go home past the leash > attack > chase > search > patrol or wander.
+// A zero distance or ratio leaves that behaviour out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnemyProfile {
+    pub wander_range: f32,
+    pub arrive_distance: f32,
+    pub idle_time: (f32, f32),
+    // Flees below this `health_ratio` until `safe_distance` away
+    pub flee_health: f32,
+    pub safe_distance: f32,
+    pub leash_distance: f32,
+    pub attack_range: f32,
+    pub attack_cooldown: f32,
+    // How far around the last known position it looks
+    pub search_radius: f32,
+    // Relative to home; wanders when empty
+    pub patrol_points: Vec<Vector2>,
+}
+
+impl EnemyProfile {
+    pub fn build(&self) -> BoxedBehavior {
+        let (min_idle, max_idle) = self.idle_time;
+        let mut children: Vec<BoxedBehavior> = Vec::new();
+
+        if self.flee_health > 0.0 {
+            children.push(Box::new(Sequence::new(vec![
+                Box::new(Condition::health_below(self.flee_health)),
+                Box::new(Flee::new(self.safe_distance)),
+            ])));
+        }
+
+        if self.leash_distance > 0.0 {
+            children.push(Box::new(ReturnHome::new(
+                self.leash_distance,
+                self.arrive_distance,
+            )));
+        }
+
+        if self.attack_range > 0.0 {
+            children.push(Box::new(Attack::new(
+                self.attack_range,
+                self.attack_cooldown,
+            )));
+        }
+
+        children.push(Box::new(Chase));
+        children.push(Box::new(Search::new(
+            self.search_radius,
+            self.arrive_distance,
+        )));
+
+        if self.patrol_points.is_empty() {
+            children.push(Box::new(RandomSelector::new(vec![
+                Box::new(Idle::new(min_idle, max_idle)),
+                Box::new(Wander::new(
+                    self.wander_range,
+                    self.arrive_distance,
+                    min_idle,
+                    max_idle,
+                )),
+            ])));
+        } else {
+            children.push(Box::new(Sequence::new(vec![
+                Box::new(Patrol::new(
+                    self.patrol_points.clone(),
+                    self.arrive_distance,
+                )),
+                Box::new(Idle::new(min_idle, max_idle)),
+            ])));
+        }
+
+        Box::new(Selector::new(children))
+    }
+}
+
+fn random_time(rng: &mut dyn RngCore, min_time: f32, max_time: f32) -> f32 {
+    if max_time > min_time {
+        rng.gen_range(min_time..=max_time)
+    } else {
+        min_time
+    }
+}
+
+// Turns the tree's action into a velocity, the same way for every enemy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Steering {
+    pub acceleration: f32,
+    pub max_speed: f32,
+    pub friction: f32,
+}
+
+impl Steering {
+    pub fn steer(&self, velocity: Vector2, bb: &Blackboard) -> Vector2 {
+        let desired = match bb.action {
+            AiAction::Stop | AiAction::Attack(_) => {
+                return velocity.move_towards(Vector2::zero(), self.friction * bb.delta);
+            }
+            AiAction::MoveTo(point) => normalized(point - bb.position),
+            AiAction::MoveAway(point) => normalized(bb.position - point),
+        };
+
+        velocity.move_towards(desired * self.max_speed, self.acceleration * bb.delta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use Status::*;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    // Returns its statuses one per tick, then the last one forever, and logs ticks and resets
+    struct Scripted {
+        name: &'static str,
+        statuses: Vec<Status>,
+        log: Log,
+    }
+
+    impl Behavior for Scripted {
+        fn tick(&mut self, _bb: &mut Blackboard, _rng: &mut dyn RngCore) -> Status {
+            self.log.borrow_mut().push(format!("tick {}", self.name));
+
+            if self.statuses.len() > 1 {
+                self.statuses.remove(0)
+            } else {
+                self.statuses[0]
+            }
+        }
+
+        fn reset(&mut self) {
+            self.log.borrow_mut().push(format!("reset {}", self.name));
+        }
+    }
+
+    fn scripted(name: &'static str, statuses: &[Status], log: &Log) -> BoxedBehavior {
+        Box::new(Scripted {
+            name,
+            statuses: statuses.to_vec(),
+            log: log.clone(),
+        })
+    }
+
+    fn log() -> Log {
+        Rc::new(RefCell::new(Vec::new()))
+    }
+
+    fn rng() -> Pcg64 {
+        Pcg64::seed_from_u64(3)
+    }
+
+    fn at(x: f32, y: f32) -> Blackboard {
+        Blackboard {
+            position: Vector2::new(x, y),
+            delta: 0.1,
+            ..Blackboard::default()
+        }
+    }
+
+    #[test]
+    fn blackboard_starts_without_target_at_full_health() {
+        let bb = Blackboard::default();
+
+        assert_eq!(bb.target, None);
+        assert_eq!(bb.last_known, None);
+        assert_eq!(bb.health_ratio, 1.0);
+        assert_eq!(bb.action, AiAction::Stop);
+        assert_eq!(at(3.0, 4.0).distance_to(Vector2::zero()), 5.0);
+    }
+
+    #[test]
+    fn selector_stops_at_the_first_child_that_does_not_fail() {
+        let log = log();
+        let mut selector = Selector::new(vec![
+            scripted("a", &[Failure], &log),
+            scripted("b", &[Success], &log),
+            scripted("c", &[Success], &log),
+        ]);
+
+        assert_eq!(selector.tick(&mut at(0.0, 0.0), &mut rng()), Success);
+        assert_eq!(*log.borrow(), vec!["tick a", "tick b"]);
+    }
+
+    #[test]
+    fn selector_fails_when_every_child_fails() {
+        let log = log();
+        let mut selector = Selector::new(vec![
+            scripted("a", &[Failure], &log),
+            scripted("b", &[Failure], &log),
+        ]);
+
+        assert_eq!(selector.tick(&mut at(0.0, 0.0), &mut rng()), Failure);
+    }
+
+    #[test]
+    fn sequence_stops_at_the_first_child_that_does_not_succeed() {
+        let log = log();
+        let mut sequence = Sequence::new(vec![
+            scripted("a", &[Success], &log),
+            scripted("b", &[Failure], &log),
+            scripted("c", &[Success], &log),
+        ]);
+
+        assert_eq!(sequence.tick(&mut at(0.0, 0.0), &mut rng()), Failure);
+        assert_eq!(*log.borrow(), vec!["tick a", "tick b"]);
+    }
+
+    #[test]
+    fn sequence_succeeds_when_every_child_succeeds() {
+        let log = log();
+        let mut sequence = Sequence::new(vec![
+            scripted("a", &[Success], &log),
+            scripted("b", &[Success], &log),
+        ]);
+
+        assert_eq!(sequence.tick(&mut at(0.0, 0.0), &mut rng()), Success);
+    }
+
+    #[test]
+    fn running_sequence_checks_its_conditions_again_every_tick() {
+        let log = log();
+        let mut sequence = Sequence::new(vec![
+            scripted("check", &[Success, Failure], &log),
+            scripted("act", &[Running], &log),
+        ]);
+        let mut bb = at(0.0, 0.0);
+
+        assert_eq!(sequence.tick(&mut bb, &mut rng()), Running);
+        assert_eq!(sequence.tick(&mut bb, &mut rng()), Failure);
+        assert_eq!(
+            *log.borrow(),
+            vec!["tick check", "tick act", "tick check", "reset act"]
+        );
+    }
+
+    #[test]
+    fn higher_priority_interrupts_and_resets_a_running_child() {
+        let log = log();
+        let mut selector = Selector::new(vec![
+            scripted("flee", &[Failure, Running], &log),
+            scripted("wander", &[Running], &log),
+        ]);
+        let mut bb = at(0.0, 0.0);
+
+        assert_eq!(selector.tick(&mut bb, &mut rng()), Running);
+        assert_eq!(selector.tick(&mut bb, &mut rng()), Running);
+        assert_eq!(
+            *log.borrow(),
+            vec!["tick flee", "tick wander", "tick flee", "reset wander"]
+        );
+    }
+
+    #[test]
+    fn child_that_keeps_running_is_not_reset() {
+        let log = log();
+        let mut selector = Selector::new(vec![
+            scripted("a", &[Failure], &log),
+            scripted("b", &[Running], &log),
+        ]);
+        let mut bb = at(0.0, 0.0);
+
+        selector.tick(&mut bb, &mut rng());
+        selector.tick(&mut bb, &mut rng());
+        assert!(!log.borrow().iter().any(|entry| entry.starts_with("reset")));
+    }
+
+    #[test]
+    fn random_selector_keeps_its_pick_until_it_finishes() {
+        let log = log();
+        let mut random = RandomSelector::new(vec![
+            scripted("a", &[Running, Success], &log),
+            scripted("b", &[Running, Success], &log),
+        ]);
+        let mut bb = at(0.0, 0.0);
+        let mut rng = rng();
+
+        assert_eq!(random.tick(&mut bb, &mut rng), Running);
+        assert_eq!(random.tick(&mut bb, &mut rng), Success);
+
+        let log = log.borrow();
+        assert_eq!(log[0], log[1]);
+    }
+
+    #[test]
+    fn random_selector_without_children_fails() {
+        let mut random = RandomSelector::new(Vec::new());
+
+        assert_eq!(random.tick(&mut at(0.0, 0.0), &mut rng()), Failure);
+    }
+
+    #[test]
+    fn health_below_compares_the_ratio() {
+        let mut condition = Condition::health_below(0.5);
+        let mut bb = at(0.0, 0.0);
+
+        bb.health_ratio = 0.4;
+        assert_eq!(condition.tick(&mut bb, &mut rng()), Success);
+        bb.health_ratio = 0.5;
+        assert_eq!(condition.tick(&mut bb, &mut rng()), Failure);
+    }
+
+    #[test]
+    fn idle_stands_still_for_its_time() {
+        let mut idle = Idle::new(0.25, 0.25);
+        let mut bb = at(0.0, 0.0);
+        bb.action = AiAction::MoveTo(Vector2::new(1.0, 0.0));
+
+        assert_eq!(idle.tick(&mut bb, &mut rng()), Running);
+        assert_eq!(bb.action, AiAction::Stop);
+        assert_eq!(idle.tick(&mut bb, &mut rng()), Running);
+        assert_eq!(idle.tick(&mut bb, &mut rng()), Success);
+    }
+
+    #[test]
+    fn wander_walks_around_home_until_it_arrives() {
+        let mut wander = Wander::new(10.0, 1.0, 5.0, 5.0);
+        let mut bb = at(200.0, 200.0);
+        bb.home = Vector2::new(100.0, 100.0);
+
+        assert_eq!(wander.tick(&mut bb, &mut rng()), Running);
+        let point = match bb.action {
+            AiAction::MoveTo(point) => point,
+            action => panic!("expected a move, got {:?}", action),
+        };
+        assert!((point.x - 100.0).abs() <= 10.0);
+        assert!((point.y - 100.0).abs() <= 10.0);
+
+        bb.position = point;
+        assert_eq!(wander.tick(&mut bb, &mut rng()), Success);
+        assert_eq!(bb.action, AiAction::Stop);
+    }
+
+    #[test]
+    fn wander_gives_up_after_its_time() {
+        let mut wander = Wander::new(10.0, 1.0, 0.15, 0.15);
+        let mut bb = at(500.0, 500.0);
+
+        assert_eq!(wander.tick(&mut bb, &mut rng()), Running);
+        assert_eq!(wander.tick(&mut bb, &mut rng()), Success);
+    }
+
+    #[test]
+    fn chase_follows_the_target_while_there_is_one() {
+        let mut bb = at(0.0, 0.0);
+
+        assert_eq!(Chase.tick(&mut bb, &mut rng()), Failure);
+
+        bb.target = Some(Vector2::new(30.0, 0.0));
+        assert_eq!(Chase.tick(&mut bb, &mut rng()), Running);
+        assert_eq!(bb.action, AiAction::MoveTo(Vector2::new(30.0, 0.0)));
+    }
+
+    #[test]
+    fn flee_runs_until_safe() {
+        let mut flee = Flee::new(50.0);
+        let mut bb = at(0.0, 0.0);
+
+        assert_eq!(flee.tick(&mut bb, &mut rng()), Failure);
+
+        bb.target = Some(Vector2::new(20.0, 0.0));
+        assert_eq!(flee.tick(&mut bb, &mut rng()), Running);
+        assert_eq!(bb.action, AiAction::MoveAway(Vector2::new(20.0, 0.0)));
+
+        bb.position = Vector2::new(-30.0, 0.0);
+        assert_eq!(flee.tick(&mut bb, &mut rng()), Success);
+    }
+
+    #[test]
+    fn search_goes_to_the_last_known_position_then_looks_around() {
+        let mut search = Search::new(8.0, 1.0);
+        let mut bb = at(0.0, 0.0);
+        let last_known = Vector2::new(40.0, 0.0);
+
+        assert_eq!(search.tick(&mut bb, &mut rng()), Failure);
+
+        bb.last_known = Some(last_known);
+        assert_eq!(search.tick(&mut bb, &mut rng()), Running);
+        assert_eq!(bb.action, AiAction::MoveTo(last_known));
+
+        bb.position = last_known;
+        assert_eq!(search.tick(&mut bb, &mut rng()), Running);
+        match bb.action {
+            AiAction::MoveTo(point) => {
+                assert!((point.x - 40.0).abs() <= 8.0);
+                assert!(point.y.abs() <= 8.0);
+            }
+            action => panic!("expected a move, got {:?}", action),
+        }
+    }
+
+    #[test]
+    fn attack_hits_in_range_then_waits_for_its_cooldown() {
+        let mut attack = Attack::new(10.0, 1.0);
+        let mut bb = at(0.0, 0.0);
+        let target = Vector2::new(5.0, 0.0);
+
+        bb.target = Some(Vector2::new(20.0, 0.0));
+        assert_eq!(attack.tick(&mut bb, &mut rng()), Failure);
+
+        bb.target = Some(target);
+        assert_eq!(attack.tick(&mut bb, &mut rng()), Success);
+        assert_eq!(bb.action, AiAction::Attack(target));
+
+        bb.time = 0.5;
+        assert_eq!(attack.tick(&mut bb, &mut rng()), Running);
+        assert_eq!(bb.action, AiAction::Stop);
+
+        bb.time = 1.0;
+        assert_eq!(attack.tick(&mut bb, &mut rng()), Success);
+    }
+
+    #[test]
+    fn patrol_loops_through_points_around_home() {
+        let mut patrol = Patrol::new(vec![Vector2::new(10.0, 0.0), Vector2::new(0.0, 10.0)], 1.0);
+        let mut bb = at(0.0, 0.0);
+        bb.home = Vector2::new(100.0, 100.0);
+
+        assert_eq!(patrol.tick(&mut bb, &mut rng()), Running);
+        assert_eq!(bb.action, AiAction::MoveTo(Vector2::new(110.0, 100.0)));
+
+        bb.position = Vector2::new(110.0, 100.0);
+        assert_eq!(patrol.tick(&mut bb, &mut rng()), Success);
+        assert_eq!(patrol.tick(&mut bb, &mut rng()), Running);
+        assert_eq!(bb.action, AiAction::MoveTo(Vector2::new(100.0, 110.0)));
+
+        bb.position = Vector2::new(100.0, 110.0);
+        assert_eq!(patrol.tick(&mut bb, &mut rng()), Success);
+        assert_eq!(patrol.tick(&mut bb, &mut rng()), Running);
+        assert_eq!(bb.action, AiAction::MoveTo(Vector2::new(110.0, 100.0)));
+    }
+
+    #[test]
+    fn return_home_walks_all_the_way_back_once_past_the_leash() {
+        let mut return_home = ReturnHome::new(50.0, 2.0);
+        let mut bb = at(40.0, 0.0);
+
+        assert_eq!(return_home.tick(&mut bb, &mut rng()), Failure);
+
+        bb.position = Vector2::new(60.0, 0.0);
+        assert_eq!(return_home.tick(&mut bb, &mut rng()), Running);
+        assert_eq!(bb.action, AiAction::MoveTo(Vector2::zero()));
+
+        // Back within the leash, but not home yet
+        bb.position = Vector2::new(30.0, 0.0);
+        assert_eq!(return_home.tick(&mut bb, &mut rng()), Running);
+
+        bb.position = Vector2::new(1.0, 0.0);
+        assert_eq!(return_home.tick(&mut bb, &mut rng()), Success);
+        assert_eq!(return_home.tick(&mut bb, &mut rng()), Failure);
+    }
+
+    fn profile() -> EnemyProfile {
+        EnemyProfile {
+            wander_range: 32.0,
+            arrive_distance: 4.0,
+            idle_time: (1.0, 1.0),
+            flee_health: 0.3,
+            safe_distance: 64.0,
+            leash_distance: 0.0,
+            attack_range: 0.0,
+            attack_cooldown: 1.0,
+            search_radius: 16.0,
+            patrol_points: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn profile_flees_when_hurt_and_chases_otherwise() {
+        let target = Vector2::new(20.0, 0.0);
+        let mut tree = profile().build();
+        let mut bb = at(0.0, 0.0);
+        bb.target = Some(target);
+
+        tree.tick(&mut bb, &mut rng());
+        assert_eq!(bb.action, AiAction::MoveTo(target));
+
+        bb.health_ratio = 0.2;
+        tree.tick(&mut bb, &mut rng());
+        assert_eq!(bb.action, AiAction::MoveAway(target));
+    }
+
+    #[test]
+    fn profile_searches_for_a_lost_target() {
+        let last_known = Vector2::new(20.0, 0.0);
+        let mut tree = profile().build();
+        let mut bb = at(0.0, 0.0);
+        bb.last_known = Some(last_known);
+
+        assert_eq!(tree.tick(&mut bb, &mut rng()), Running);
+        assert_eq!(bb.action, AiAction::MoveTo(last_known));
+    }
+
+    fn assert_near(velocity: Vector2, expected: Vector2) {
+        assert!(
+            (velocity - expected).length() < 1e-4,
+            "{:?} is not {:?}",
+            velocity,
+            expected
+        );
+    }
+
+    #[test]
+    fn steering_accelerates_towards_the_point_and_brakes_on_stop() {
+        let steering = Steering {
+            acceleration: 100.0,
+            max_speed: 50.0,
+            friction: 200.0,
+        };
+        let mut bb = at(0.0, 0.0);
+
+        bb.action = AiAction::MoveTo(Vector2::new(10.0, 0.0));
+        assert_eq!(
+            steering.steer(Vector2::zero(), &bb),
+            Vector2::new(10.0, 0.0)
+        );
+
+        bb.action = AiAction::MoveAway(Vector2::new(10.0, 0.0));
+        assert_eq!(
+            steering.steer(Vector2::zero(), &bb),
+            Vector2::new(-10.0, 0.0)
+        );
+
+        bb.action = AiAction::Stop;
+        assert_near(
+            steering.steer(Vector2::new(30.0, 0.0), &bb),
+            Vector2::new(10.0, 0.0),
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+
+use gdnative::api::*;
+use gdnative::prelude::*;
+
+use crate::utils::get_instance;
+
+const AUDIO_MANAGER_PATH: &str = "/root/AudioManager";
+const MUSIC_BUS: &str = "Music";
+const SFX_BUS: &str = "SFX";
+// Volume used for "off", as in the editor's bus slider
+const SILENT_DB: f64 = -80.0;
+
+// Names accepted by `play_sfx`
+const SOUNDS: [(&str, &str); 5] = [
+    ("enemy_die", "res://Music and Sounds/EnemyDie.wav"),
+    ("evade", "res://Music and Sounds/Evade.wav"),
+    ("hit", "res://Music and Sounds/Hit.wav"),
+    ("hurt", "res://Music and Sounds/Hurt.wav"),
+    ("swipe", "res://Music and Sounds/Swipe.wav"),
+];
+
+// AudioManager "class".
+// Autoloaded singleton playing music on the "Music" bus and positional sound
+// effects on the "SFX" bus from a fixed pool of players.
+#[derive(NativeClass)]
+#[inherit(Node)]
+pub struct AudioManager {
+    #[property(default = 8)]
+    sfx_pool_size: i64,
+    #[property(default = 1.0)]
+    crossfade_duration: f64,
+    // Played on startup, e.g. res://Music and Sounds/Music.mp3
+    #[property]
+    music: String,
+
+    sounds: HashMap<String, Ref<AudioStream>>,
+    sfx_players: Vec<Ref<AudioStreamPlayer2D>>,
+    next_sfx_player: usize,
+    // Two players so one can fade out while the other fades in
+    music_players: Vec<Ref<AudioStreamPlayer>>,
+    current_music_player: usize,
+    crossfade_time: Option<f64>,
+}
+
+#[gdnative::methods]
+impl AudioManager {
+    fn new(_owner: &Node) -> Self {
+        AudioManager {
+            sfx_pool_size: 8,
+            crossfade_duration: 1.0,
+            music: String::new(),
+
+            sounds: HashMap::new(),
+            sfx_players: Vec::new(),
+            next_sfx_player: 0,
+            music_players: Vec::new(),
+            current_music_player: 0,
+            crossfade_time: None,
+        }
+    }
+
+    #[export]
+    fn _ready(&mut self, owner: &Node) {
+        ensure_bus(MUSIC_BUS);
+        ensure_bus(SFX_BUS);
+
+        for (name, path) in SOUNDS.iter() {
+            match load_stream(path) {
+                Some(stream) => {
+                    self.sounds.insert(name.to_string(), stream);
+                }
+                None => godot_print!("Could not load sound {}. Check name.", path),
+            }
+        }
+
+        for _ in 0..self.sfx_pool_size.max(1) {
+            let player = AudioStreamPlayer2D::new().into_shared();
+            unsafe { player.assume_safe() }.set_bus(SFX_BUS);
+
+            owner.add_child(player.clone(), false);
+            self.sfx_players.push(player);
+        }
+
+        for _ in 0..2 {
+            let player = AudioStreamPlayer::new().into_shared();
+            unsafe { player.assume_safe() }.set_bus(MUSIC_BUS);
+
+            owner.add_child(player.clone(), false);
+            self.music_players.push(player);
+        }
+
+        if !self.music.is_empty() {
+            self.play_music(owner, self.music.clone());
+        }
+    }
+
+    #[export]
+    fn _process(&mut self, _owner: &Node, delta: f64) {
+        let time = match self.crossfade_time {
+            Some(time) => time + delta,
+            None => return,
+        };
+
+        let progress = if self.crossfade_duration > 0.0 {
+            num::clamp(time / self.crossfade_duration, 0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        let fading_in = unsafe { self.music_players[self.current_music_player].assume_safe() };
+        let fading_out = unsafe { self.music_players[1 - self.current_music_player].assume_safe() };
+
+        fading_in.set_volume_db(linear_to_db(progress));
+        fading_out.set_volume_db(linear_to_db(1.0 - progress));
+
+        if progress >= 1.0 {
+            fading_out.stop();
+            self.crossfade_time = None;
+        } else {
+            self.crossfade_time = Some(time);
+        }
+    }
+
+    // Crossfades from the current track to the one at `path`
+    #[export]
+    pub fn play_music(&mut self, _owner: &Node, path: String) {
+        let stream = match load_stream(&path) {
+            Some(stream) => stream,
+            None => {
+                godot_print!("Could not load music {}. Check name.", path);
+                return;
+            }
+        };
+
+        self.current_music_player = 1 - self.current_music_player;
+
+        let player = unsafe { self.music_players[self.current_music_player].assume_safe() };
+        player.set_stream(stream);
+        player.set_volume_db(SILENT_DB);
+        player.play(0.0);
+
+        self.crossfade_time = Some(0.0);
+    }
+
+    #[export]
+    pub fn play_sfx(&mut self, _owner: &Node, name: String, position: Vector2) {
+        let stream = match self.sounds.get(&name) {
+            Some(stream) => stream.clone(),
+            None => {
+                godot_print!("Unknown sound {}", name);
+                return;
+            }
+        };
+
+        let player = self.free_sfx_player();
+        player.set_stream(stream);
+        player.set_global_position(position);
+        player.play(0.0);
+    }
+
+    // `bus` is "master", "music" or "sfx"; `volume` is linear, from 0.0 to 1.0
+    #[export]
+    pub fn set_bus_volume(&self, _owner: &Node, bus: String, volume: f64) {
+        if let Some(index) = bus_index(&bus) {
+            let volume = linear_to_db(num::clamp(volume, 0.0, 1.0));
+            AudioServer::godot_singleton().set_bus_volume_db(index, volume);
+        }
+    }
+
+    #[export]
+    pub fn get_bus_volume(&self, _owner: &Node, bus: String) -> f64 {
+        match bus_index(&bus) {
+            Some(index) => db_to_linear(AudioServer::godot_singleton().get_bus_volume_db(index)),
+            None => 0.0,
+        }
+    }
+}
+
+impl AudioManager {
+    // First idle player, or the one that has been playing the longest
+    fn free_sfx_player(&mut self) -> TRef<'_, AudioStreamPlayer2D> {
+        let idle = self
+            .sfx_players
+            .iter()
+            .position(|player| !unsafe { player.assume_safe() }.is_playing());
+
+        let index = match idle {
+            Some(index) => index,
+            None => {
+                let index = self.next_sfx_player;
+                self.next_sfx_player = (index + 1) % self.sfx_players.len();
+                index
+            }
+        };
+
+        unsafe { self.sfx_players[index].assume_safe() }
+    }
+}
+
+fn bus_index(bus: &str) -> Option<i64> {
+    let name = match bus {
+        "master" => "Master",
+        "music" => MUSIC_BUS,
+        "sfx" => SFX_BUS,
+        _ => {
+            godot_print!("Unknown audio bus {}", bus);
+            return None;
+        }
+    };
+
+    let index = AudioServer::godot_singleton().get_bus_index(name);
+    if index < 0 {
+        None
+    } else {
+        Some(index)
+    }
+}
+
+// Adds `name` routed to Master, unless the bus layout already has it
+fn ensure_bus(name: &str) {
+    let audio_server = AudioServer::godot_singleton();
+
+    if audio_server.get_bus_index(name) >= 0 {
+        return;
+    }
+
+    let index = audio_server.bus_count();
+    audio_server.add_bus(index);
+    audio_server.set_bus_name(index, name);
+    audio_server.set_bus_send(index, "Master");
+}
+
+fn linear_to_db(linear: f64) -> f64 {
+    if linear <= 0.0 {
+        SILENT_DB
+    } else {
+        (20.0 * linear.log10()).max(SILENT_DB)
+    }
+}
+
+fn db_to_linear(db: f64) -> f64 {
+    10.0_f64.powf(db / 20.0)
+}
+
+fn load_stream(path: &str) -> Option<Ref<AudioStream>> {
+    let stream = ResourceLoader::godot_singleton().load(path, "AudioStream", false)?;
+    let stream = unsafe { stream.assume_unique().into_shared() };
+    stream.cast::<AudioStream>()
+}
+
+// Plays the sound `name` at `position` through the `AudioManager` singleton
+pub fn play_sfx(node: &Node, name: &str, position: Vector2) {
+    // The game goes on in silence
+    let audio_manager = match get_instance::<AudioManager>(node, AUDIO_MANAGER_PATH) {
+        Ok(audio_manager) => audio_manager,
+        Err(error) => {
+            godot_print!("{}, {} is not played", error, name);
+            return;
+        }
+    };
+
+    unsafe { audio_manager.assume_safe() }
+        .map_mut(|audio_manager, owner| audio_manager.play_sfx(&owner, name.to_string(), position))
+        .expect("AudioManager should not be borrowed");
+}
+
+fn with_audio_manager<R>(node: &Node, f: impl FnOnce(&AudioManager, TRef<Node>) -> R) -> Option<R> {
+    let audio_manager = node.get_node(AUDIO_MANAGER_PATH)?;
+    let audio_manager = unsafe { audio_manager.assume_safe() };
+
+    audio_manager.cast_instance::<AudioManager>()?.map(f).ok()
+}
+
+// Linear volume of `bus` through the `AudioManager` singleton, 0.0 without it
+pub fn bus_volume(node: &Node, bus: &str) -> f64 {
+    with_audio_manager(node, |audio_manager, owner| {
+        audio_manager.get_bus_volume(&owner, bus.to_string())
+    })
+    .unwrap_or(0.0)
+}
+
+pub fn set_bus_volume(node: &Node, bus: &str, volume: f64) {
+    with_audio_manager(node, |audio_manager, owner| {
+        audio_manager.set_bus_volume(&owner, bus.to_string(), volume)
+    });
+}
@@ -1,11 +1,22 @@
 use gdnative::api::*;
 use gdnative::prelude::*;
-use rand::prelude::*;
-use rand_pcg::Pcg64;
-
-use crate::utils::load_scene;
+use rand::Rng;
+
+use crate::ai::*;
+use crate::audio_manager;
+use crate::damage::DamageInfo;
+use crate::detection_zone::DetectionZone;
+use crate::pathfinder::query_path;
+use crate::pathfinding::PathFollower;
+use crate::perception::*;
+use crate::rng_service::with_rng;
+use crate::save_game;
+use crate::soft_collision::SoftCollision;
+use crate::stats::Stats;
+use crate::utils::{disable, get_instance, get_ref, get_typed, load_scene, normalized, NodeError};
 
 // Bat "class".
+// Its behaviour is the standard `EnemyProfile` tree, tuned by the `ai/*` properties.
 #[derive(NativeClass)]
 #[inherit(KinematicBody2D)]
 pub struct Bat {
@@ -15,27 +26,63 @@ pub struct Bat {
     max_speed: f32,
     #[property(default = 200.0)]
     friction: f32,
-    #[property(default = 4)]
-    wander_target_range: i32,
+    // Whether killing this bat is remembered by `SaveGame`; off for spawned bats
+    #[property(default = true)]
+    persistent: bool,
+    #[property(path = "ai/wander_range", default = 32.0)]
+    wander_range: f32,
+    #[property(path = "ai/arrive_distance", default = 4.0)]
+    arrive_distance: f32,
+    #[property(path = "ai/idle_min", default = 1.0)]
+    idle_min: f32,
+    #[property(path = "ai/idle_max", default = 3.0)]
+    idle_max: f32,
+    // Health ratio below which it flees, 0 = never
+    #[property(path = "ai/flee_health", default = 0.0)]
+    flee_health: f32,
+    #[property(path = "ai/safe_distance", default = 64.0)]
+    safe_distance: f32,
+    // Distance from home at which it gives up and goes back, 0 = never
+    #[property(path = "ai/leash_distance", default = 0.0)]
+    leash_distance: f32,
+    // Range of the swoop attack, 0 = none
+    #[property(path = "ai/attack_range", default = 0.0)]
+    attack_range: f32,
+    #[property(path = "ai/attack_cooldown", default = 1.0)]
+    attack_cooldown: f32,
+    // Seconds the player must stay in sight before it gives chase
+    #[property(path = "ai/notice_time", default = 0.2)]
+    notice_time: f32,
+    // Seconds out of sight before the chase becomes a search
+    #[property(path = "ai/lose_time", default = 1.0)]
+    lose_time: f32,
+    // Seconds spent searching before giving up
+    #[property(path = "ai/search_time", default = 3.0)]
+    search_time: f32,
+    #[property(path = "ai/search_radius", default = 16.0)]
+    search_radius: f32,
+    // Seconds between two path searches towards a moving point
+    #[property(path = "ai/repath_interval", default = 0.25)]
+    repath_interval: f32,
+    // Patrol route relative to the start position; wanders when empty
+    #[property(path = "ai/patrol_points")]
+    patrol_points: Vector2Array,
 
     velocity: Vector2,
     knockback: Vector2,
-    stats: Ref<Node>,
+    // Found in `_ready`, like the other instances
+    stats: Option<Instance<Stats, Shared>>,
+    // Read in `_ready`, `no_health` comes while `Stats` may still be borrowed
+    xp_reward: i64,
     effect_scene_load: Ref<PackedScene>,
-    state: BatState,
-    // player_detecion_zone: Ref<Node>,
-    sprite: Ref<Node>,
-    player: Ref<Node>,
-    hurtbox: Ref<Node>,
-    soft_collision: Ref<Node>,
-    wander_controller: Ref<Node>,
-    animation_player: Ref<Node>,
-}
-
-enum BatState {
-    Idle,
-    Wander,
-    Chase,
+    behavior: BoxedBehavior,
+    blackboard: Blackboard,
+    memory: TargetMemory,
+    path: PathFollower,
+    detection_zone: Option<Instance<DetectionZone, Shared>>,
+    sprite: Ref<AnimatedSprite>,
+    soft_collision: Option<Instance<SoftCollision, Shared>>,
+    animation_player: Ref<AnimationPlayer>,
 }
 
 // Bat Implementation
@@ -47,26 +94,53 @@ impl Bat {
             acceleration: 300.0,
             max_speed: 50.0,
             friction: 200.0,
-            wander_target_range: 4,
+            persistent: true,
+            wander_range: 32.0,
+            arrive_distance: 4.0,
+            idle_min: 1.0,
+            idle_max: 3.0,
+            flee_health: 0.0,
+            safe_distance: 64.0,
+            leash_distance: 0.0,
+            attack_range: 0.0,
+            attack_cooldown: 1.0,
+            notice_time: 0.2,
+            lose_time: 1.0,
+            search_time: 3.0,
+            search_radius: 16.0,
+            repath_interval: 0.25,
+            patrol_points: Vector2Array::new(),
 
             velocity: Vector2::zero(),
             knockback: Vector2::zero(),
 
-            stats: Node::new().into_shared(),
+            stats: None,
+            xp_reward: 0,
             effect_scene_load: PackedScene::new().into_shared(),
-            state: BatState::Idle,
-            // player_detecion_zone: Node::new().into_shared(),
-            sprite: Node::new().into_shared(),
-            player: Node::new().into_shared(),
-            hurtbox: Node::new().into_shared(),
-            soft_collision: Node::new().into_shared(),
-            wander_controller: Node::new().into_shared(),
-            animation_player: Node::new().into_shared(),
+            behavior: Box::new(Chase),
+            blackboard: Blackboard::default(),
+            memory: TargetMemory::new(MemoryTimes {
+                notice_time: 0.2,
+                lose_time: 1.0,
+                search_time: 3.0,
+            }),
+            path: PathFollower::new(0.25, 4.0),
+            detection_zone: None,
+            sprite: AnimatedSprite::new().into_shared(),
+            soft_collision: None,
+            animation_player: AnimationPlayer::new().into_shared(),
         }
     }
 
     #[export]
     fn _ready(&mut self, owner: TRef<KinematicBody2D>) {
+        // Already killed in the saved game
+        if self.persistent && save_game::is_consumed(&owner) {
+            owner.set_physics_process(false);
+            owner.queue_free();
+            return;
+        }
+
         // Loading scene
         let effect_scene_load = load_scene("res://Effects/EnemyDeathEffect.tscn");
         match effect_scene_load {
@@ -74,12 +148,19 @@ impl Bat {
             None => godot_print!("Could not load child scene. Check name."),
         }
 
-        // Access to `Stats` node
-        self.stats = owner.get_node("Stats").expect("Stats node should exist");
-        let stats = unsafe { self.stats.assume_safe() };
+        if let Err(error) = self.find_nodes(&owner) {
+            disable(&owner, error);
+            return;
+        }
+
+        let stats = match self.stats() {
+            Some(stats) => stats,
+            None => return,
+        };
 
         // Connecting to signal
         stats
+            .base()
             .connect(
                 "no_health",
                 owner,
@@ -91,47 +172,25 @@ impl Bat {
 
         // Set `max_health` and `health` variable in `Stats` node
         // stats.set("max_health", 2);
-        stats.set("health", stats.get("max_health"));
-
-        // Access to `PlayerDetectionZone` node
-        // self.player_detecion_zone = owner
-        //     .get_node("PlayerDetectionZone")
-        //     .expect("Stats node should exist");
+        stats
+            .map_mut(|stats, owner| stats.set_health(&owner, stats.max_health()))
+            .expect("Stats should not be borrowed");
 
-        // Access to `AnimatedSprite` node
-        self.sprite = owner
-            .get_node("AnimatedSprite")
-            .expect("AnimatedSprite node should exist");
+        self.xp_reward = stats
+            .map(|stats, _| stats.xp_reward())
+            .expect("Stats should not be borrowed");
 
         let sprite = unsafe { self.sprite.assume_safe() };
-        let sprite = sprite
-            .cast::<AnimatedSprite>()
-            .expect("Node should cast to AnimatedSprite");
-
-        let mut rng = Pcg64::from_rng(thread_rng()).unwrap();
-        sprite.set_frame(rng.gen_range(0..4));
-
-        // Access to `Hurtbox` node
-        self.hurtbox = owner
-            .get_node("Hurtbox")
-            .expect("Hurtbox node should exist");
-
-        // Access to `SoftCollision` node
-        self.soft_collision = owner
-            .get_node("SoftCollision")
-            .expect("SoftCollision node should exist");
-
-        // Access to `WanderController` node
-        self.wander_controller = owner
-            .get_node("WanderController")
-            .expect("WanderController node should exist");
-
-        self.state = self.pick_random_state(&mut vec![BatState::Idle, BatState::Wander]);
-
-        // Access `AnimationPlayer` node
-        self.animation_player = owner
-            .get_node("AnimationPlayer")
-            .expect("AnimationPlayer node Should Exist");
+        sprite.set_frame(with_rng(&owner, "bat", |rng| rng.gen_range(0..4)));
+
+        self.behavior = self.profile().build();
+        self.memory = TargetMemory::new(MemoryTimes {
+            notice_time: self.notice_time,
+            lose_time: self.lose_time,
+            search_time: self.search_time,
+        });
+        self.path = PathFollower::new(self.repath_interval, self.arrive_distance);
+        self.blackboard.home = owner.global_position();
     }
 
     #[export]
@@ -149,66 +208,47 @@ impl Bat {
             true,
         );
 
-        match self.state {
-            BatState::Idle => {
-                self.velocity = self
-                    .velocity
-                    .move_towards(Vector2::zero(), self.friction * delta as f32);
-
-                let wander_controller = unsafe { self.wander_controller.assume_safe() };
-                if unsafe { wander_controller.call("get_time_left", &[]).to_f64() } == 0.0 {
-                    self.update_wander();
-                }
-            }
-            BatState::Wander => {
-                let wander_controller = unsafe { self.wander_controller.assume_safe() };
-                if unsafe { wander_controller.call("get_time_left", &[]).to_f64() } == 0.0 {
-                    self.update_wander();
-                }
-
-                // self.accelerate_towards_point(
-                //     owner,
-                //     unsafe {
-                //         wander_controller
-                //             .call("get_target_position", &[])
-                //             .to_vector2()
-                //     },
-                //     delta,
-                // );
-
-                let pos = unsafe {
-                    wander_controller
-                        .call("get_target_position", &[])
-                        .to_vector2()
-                };
-
-                self.velocity = self.velocity.move_towards(
-                    owner.global_position().direction_to(pos) * self.max_speed,
-                    owner.global_position().distance_to(pos) * delta as f32,
-                );
-
-                if owner.global_position().distance_to(unsafe {
-                    wander_controller
-                        .call("get_target_position", &[])
-                        .to_vector2()
-                }) <= self.wander_target_range as f32
-                {
-                    self.update_wander();
-                }
+        self.update_blackboard(owner, delta as f32);
+        with_rng(owner, "bat", |rng| {
+            self.behavior.tick(&mut self.blackboard, rng)
+        });
+
+        // Around obstacles rather than straight at the point
+        match self.blackboard.action {
+            AiAction::MoveTo(point) => {
+                let waypoint =
+                    self.path
+                        .next(owner.global_position(), point, delta as f32, |from, to| {
+                            query_path(owner, from, to)
+                        });
+                self.blackboard.action = AiAction::MoveTo(waypoint);
             }
-            BatState::Chase => {
-                let player = unsafe { self.player.assume_safe() };
-                let player = player.cast::<Node2D>().expect("Node should cast to Node2D");
+            _ => self.path.clear(),
+        }
 
-                self.accelerate_towards_point(owner, player.global_position(), delta);
-            }
+        let steering = Steering {
+            acceleration: self.acceleration,
+            max_speed: self.effective_max_speed(),
+            friction: self.friction,
+        };
+        self.velocity = steering.steer(self.velocity, &self.blackboard);
+
+        // Swoop at the target, the `Hitbox` does the damage
+        if let AiAction::Attack(point) = self.blackboard.action {
+            self.velocity = normalized(point - owner.global_position()) * steering.max_speed * 2.0;
         }
 
-        let soft_collision = unsafe { self.soft_collision.assume_safe() };
-        if unsafe { soft_collision.call("is_colliding", &[]).to_bool() } {
-            self.velocity += unsafe { soft_collision.call("get_push_vector", &[]).to_vector2() }
-                * delta as f32
-                * 400.0;
+        let sprite = unsafe { self.sprite.assume_safe() };
+        if self.velocity.x != 0.0 {
+            sprite.set_flip_h(self.velocity.x < 0.0);
+        }
+
+        if let Some(soft_collision) = &self.soft_collision {
+            let push_vector = unsafe { soft_collision.assume_safe() }
+                .map(|soft_collision, owner| soft_collision.get_push_vector(&owner))
+                .unwrap_or_else(|_| Vector2::zero());
+
+            self.velocity += push_vector * delta as f32 * 400.0;
         }
 
         self.velocity = owner.move_and_slide(
@@ -221,26 +261,10 @@ impl Bat {
         );
     }
 
-    // Accepting signal
+    // Accepting signal from the `Health` node
     #[export]
-    fn _on_hurtbox_area_entered(&mut self, _owner: &KinematicBody2D, area: Ref<Area2D>) {
-        let stats = unsafe { self.stats.assume_safe() };
-        let stats = stats.cast::<Node>().expect("Node should cast to Node");
-
-        let area = unsafe { area.assume_safe() };
-
-        // Update `health` variable in `Stats` node
-        let health = (stats.get("health").to_i64() - area.get("damage").to_i64()).to_variant();
-
-        unsafe {
-            stats.call("set_health", &[health]);
-        }
-
-        self.knockback = area.get("knockback_vector").to_vector2() * 120.0;
-
-        let hurtbox = unsafe { self.hurtbox.assume_safe() };
-        unsafe { hurtbox.call("create_hit_effect", &[]) };
-        unsafe { hurtbox.call("start_invincibility", &[(0.4).to_variant()]) };
+    fn _on_health_damaged(&mut self, _owner: &KinematicBody2D, damage: DamageInfo) {
+        self.knockback = damage.knockback * 120.0;
     }
 
     // Accepting signal
@@ -248,6 +272,26 @@ impl Bat {
     fn _on_stats_no_health(&self, owner: &KinematicBody2D) {
         //Deleting Bat node
         owner.queue_free();
+        if self.persistent {
+            save_game::mark_consumed(owner);
+        }
+        audio_manager::play_sfx(owner, "enemy_die", owner.global_position());
+
+        // Experience for the player
+        match get_instance::<Stats>(owner, "/root/PlayerStats") {
+            Ok(player_stats) => unsafe { player_stats.assume_safe() }
+                .map_mut(|player_stats, owner| player_stats.add_xp(&owner, self.xp_reward))
+                .expect("PlayerStats should not be borrowed"),
+            Err(error) => godot_print!("{}, no experience gained", error),
+        }
+
+        // Dies all the same without loot
+        match get_typed::<Node>(owner, "LootDropper") {
+            Ok(loot_dropper) => unsafe {
+                loot_dropper.call("drop_loot", &[owner.global_position().to_variant()]);
+            },
+            Err(error) => godot_print!("{}", error),
+        }
 
         let enemy_death_effect = unsafe { self.effect_scene_load.assume_safe() };
         let enemy_death_effect = enemy_death_effect
@@ -269,74 +313,96 @@ impl Bat {
         enemy_death_effect.set_global_position(owner.global_position());
     }
 
-    #[export]
-    fn _on_player_detection_zone_body_entered(
-        &mut self,
-        _owner: &KinematicBody2D,
-        body: Ref<Node>,
-    ) {
-        self.state = BatState::Chase;
-        self.player = body;
-    }
-
-    #[export]
-    fn _on_player_detection_zone_body_exited(
-        &mut self,
-        _owner: &KinematicBody2D,
-        _body: Ref<Node>,
-    ) {
-        self.state = BatState::Idle;
-        self.player = Node::new().into_shared()
-    }
-
     #[export]
     fn _on_hurtbox_invincibility_started(&self, _owner: &KinematicBody2D) {
         let animation_player = unsafe { self.animation_player.assume_safe() };
-        let animation_player = animation_player.cast::<AnimationPlayer>().unwrap();
 
         animation_player.play("start", -1.0, 1.0, false)
     }
     #[export]
     fn _on_hurtbox_invincibility_ended(&self, _owner: &KinematicBody2D) {
         let animation_player = unsafe { self.animation_player.assume_safe() };
-        let animation_player = animation_player.cast::<AnimationPlayer>().unwrap();
 
         animation_player.play("stop", -1.0, 1.0, false)
     }
 }
 
 impl Bat {
-    fn pick_random_state(&self, state_list: &mut Vec<BatState>) -> BatState {
-        state_list.shuffle(&mut thread_rng());
-        state_list.remove(0)
+    fn find_nodes(&mut self, owner: &KinematicBody2D) -> Result<(), NodeError> {
+        self.stats = Some(get_instance::<Stats>(owner, "Stats")?);
+        self.detection_zone = Some(get_instance::<DetectionZone>(owner, "DetectionZone")?);
+        self.sprite = get_ref::<AnimatedSprite>(owner, "AnimatedSprite")?;
+        self.soft_collision = Some(get_instance::<SoftCollision>(owner, "SoftCollision")?);
+        self.animation_player = get_ref::<AnimationPlayer>(owner, "AnimationPlayer")?;
+
+        Ok(())
     }
 
-    fn update_wander(&mut self) {
-        let wander_controller = unsafe { self.wander_controller.assume_safe() };
-        self.state = self.pick_random_state(&mut vec![BatState::Idle, BatState::Wander]);
-
-        unsafe {
-            wander_controller.call(
-                "start_wander_timer",
-                &[RandomNumberGenerator::new()
-                    .randf_range(1.0, 3.0)
-                    .to_variant()],
-            )
-        };
+    // `Stats`, None once `_ready` disabled the bat
+    fn stats(&self) -> Option<RefInstance<'_, Stats, Shared>> {
+        self.stats
+            .as_ref()
+            .map(|stats| unsafe { stats.assume_safe() })
     }
 
-    fn accelerate_towards_point(&mut self, owner: &KinematicBody2D, point: Vector2, delta: f64) {
-        let direction = owner.global_position().direction_to(point);
-
-        self.velocity = self
-            .velocity
-            .move_towards(direction * self.max_speed, self.acceleration * delta as f32);
+    // `max_speed` after slows and stuns on `Stats`
+    fn effective_max_speed(&self) -> f32 {
+        let multiplier = self
+            .stats()
+            .and_then(|stats| {
+                stats
+                    .map(|stats, owner| stats.get_speed_multiplier(&owner))
+                    .ok()
+            })
+            .unwrap_or(1.0);
+
+        self.max_speed * multiplier
+    }
 
-        let sprite = unsafe { self.sprite.assume_safe() };
-        let sprite = sprite
-            .cast::<AnimatedSprite>()
-            .expect("Node should cast to AnimatedSprite");
+    fn profile(&self) -> EnemyProfile {
+        EnemyProfile {
+            wander_range: self.wander_range,
+            arrive_distance: self.arrive_distance,
+            idle_time: (self.idle_min, self.idle_max),
+            flee_health: self.flee_health,
+            safe_distance: self.safe_distance,
+            leash_distance: self.leash_distance,
+            attack_range: self.attack_range,
+            attack_cooldown: self.attack_cooldown,
+            search_radius: self.search_radius,
+            patrol_points: self.patrol_points.read().to_vec(),
+        }
+    }
 
-        sprite.set_flip_h(self.velocity.x < 0.0);
+    fn update_blackboard(&mut self, owner: &KinematicBody2D, delta: f32) {
+        let (health, max_health) = self
+            .stats()
+            .and_then(|stats| {
+                stats
+                    .map(|stats, _| (stats.health(), stats.max_health()))
+                    .ok()
+            })
+            .unwrap_or((1, 1));
+
+        // The closest target the detection zone can see, if any
+        let seen = self
+            .detection_zone
+            .as_ref()
+            .and_then(|zone| {
+                unsafe { zone.assume_safe() }
+                    .map(|zone, owner| zone.nearest_visible(&owner))
+                    .ok()
+                    .flatten()
+            })
+            .map(|(_, position)| position);
+        self.memory.update(seen, delta);
+
+        let blackboard = &mut self.blackboard;
+        blackboard.position = owner.global_position();
+        blackboard.target = self.memory.target();
+        blackboard.last_known = self.memory.last_known();
+        blackboard.health_ratio = health as f32 / max_health.max(1) as f32;
+        blackboard.delta = delta;
+        blackboard.time += delta;
     }
 }
@@ -0,0 +1,173 @@
+use gdnative::api::*;
+use gdnative::prelude::*;
+use rand::Rng;
+
+use crate::rng_service::with_rng;
+use crate::utils::{get_typed, load_scene};
+
+// BatBenchmark "class".
+// Fills the `Bats` child with `count` bats inside `spawn_extents` and prints the
+// average and worst process and physics time per frame, measured for `duration`
+// seconds after `warmup`. Without a player around the bats wander, so it shows
+// the baseline cost of their AI, perception and movement.
+#[derive(NativeClass)]
+#[inherit(Node2D)]
+pub struct BatBenchmark {
+    #[property]
+    bat_scene: String,
+    #[property(default = 200)]
+    count: i64,
+    #[property]
+    spawn_extents: Vector2,
+    #[property(default = 1.0)]
+    warmup: f64,
+    #[property(default = 5.0)]
+    duration: f64,
+    // Closes the game once the result is printed, e.g. when run from a script
+    #[property(default = false)]
+    quit_when_done: bool,
+
+    elapsed: f64,
+    frames: u32,
+    process_time: Sample,
+    physics_time: Sample,
+    done: bool,
+}
+
+// Seconds per frame, summed up and the worst one
+#[derive(Default)]
+struct Sample {
+    total: f64,
+    max: f64,
+}
+
+impl Sample {
+    fn add(&mut self, time: f64) {
+        self.total += time;
+        self.max = self.max.max(time);
+    }
+
+    // Average and worst in milliseconds
+    fn millis(&self, frames: u32) -> (f64, f64) {
+        (
+            self.total / frames.max(1) as f64 * 1000.0,
+            self.max * 1000.0,
+        )
+    }
+}
+
+#[gdnative::methods]
+impl BatBenchmark {
+    // The "constructor" of the class.
+    fn new(_owner: &Node2D) -> Self {
+        BatBenchmark {
+            bat_scene: "res://Enemies/Bat.tscn".to_string(),
+            count: 200,
+            spawn_extents: Vector2::new(160.0, 90.0),
+            warmup: 1.0,
+            duration: 5.0,
+            quit_when_done: false,
+
+            elapsed: 0.0,
+            frames: 0,
+            process_time: Sample::default(),
+            physics_time: Sample::default(),
+            done: false,
+        }
+    }
+
+    #[export]
+    fn _ready(&mut self, owner: &Node2D) {
+        let bats = match get_typed::<Node2D>(owner, "Bats") {
+            Ok(bats) => bats,
+            Err(error) => {
+                godot_print!("{}", error);
+                owner.set_process(false);
+                return;
+            }
+        };
+
+        let scene = match load_scene(&self.bat_scene) {
+            Some(scene) => scene,
+            None => {
+                godot_print!("Could not load {}. Check bat_scene.", self.bat_scene);
+                owner.set_process(false);
+                return;
+            }
+        };
+        let scene = unsafe { scene.assume_safe() };
+
+        let extents = self.spawn_extents;
+        let positions: Vec<Vector2> = with_rng(owner, "benchmark", |rng| {
+            (0..self.count)
+                .map(|_| {
+                    Vector2::new(
+                        rng.gen_range(-extents.x..=extents.x),
+                        rng.gen_range(-extents.y..=extents.y),
+                    )
+                })
+                .collect()
+        });
+
+        for position in positions {
+            let bat = scene
+                .instance(PackedScene::GEN_EDIT_STATE_DISABLED)
+                .expect("should be able to instance scene");
+            let bat = unsafe { bat.assume_safe() };
+
+            // Not part of the saved world
+            bat.set("persistent", false);
+
+            if let Some(bat) = bat.cast::<Node2D>() {
+                bat.set_position(position);
+            }
+
+            bats.add_child(bat, false);
+        }
+    }
+
+    #[export]
+    fn _process(&mut self, owner: &Node2D, delta: f64) {
+        if self.done {
+            return;
+        }
+
+        self.elapsed += delta;
+        if self.elapsed < self.warmup {
+            return;
+        }
+
+        let performance = Performance::godot_singleton();
+        self.process_time
+            .add(performance.get_monitor(Performance::TIME_PROCESS));
+        self.physics_time
+            .add(performance.get_monitor(Performance::TIME_PHYSICS_PROCESS));
+        self.frames += 1;
+
+        if self.elapsed >= self.warmup + self.duration {
+            self.done = true;
+            self.report();
+
+            if self.quit_when_done {
+                unsafe { owner.get_tree().unwrap().assume_safe().quit(0) };
+            }
+        }
+    }
+}
+
+impl BatBenchmark {
+    fn report(&self) {
+        let (process_average, process_max) = self.process_time.millis(self.frames);
+        let (physics_average, physics_max) = self.physics_time.millis(self.frames);
+
+        godot_print!(
+            "BatBenchmark: {} bats over {} frames, process {:.3} ms (max {:.3} ms), physics {:.3} ms (max {:.3} ms)",
+            self.count,
+            self.frames,
+            process_average,
+            process_max,
+            physics_average,
+            physics_max
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use gdnative::api::*;
+use gdnative::prelude::*;
+
+use crate::save_game;
+use crate::stats::Stats;
+use crate::utils::get_instance;
+
+// Checkpoint "class".
+// Becomes the place the player respawns at when the player walks in. The active
+// one is kept in the saved world, so it is the same after loading the game.
+#[derive(NativeClass)]
+#[inherit(Area2D)]
+#[register_with(Self::register_signals)]
+pub struct Checkpoint {
+    // Refills `PlayerStats` health every time the player walks in
+    #[property(default = true)]
+    heal: bool,
+    // Writes the save file when it becomes active
+    #[property(default = true)]
+    save: bool,
+
+    active: bool,
+}
+
+#[gdnative::methods]
+impl Checkpoint {
+    // The "constructor" of the class.
+    fn new(_owner: &Area2D) -> Self {
+        Checkpoint {
+            heal: true,
+            save: true,
+
+            active: false,
+        }
+    }
+
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder.add_signal(Signal {
+            name: "activated",
+            args: &[],
+        });
+    }
+
+    #[export]
+    fn _ready(&mut self, owner: TRef<Area2D>) {
+        owner.add_to_group("checkpoints", false);
+        self.active = save_game::is_active_checkpoint(&owner);
+
+        owner
+            .connect(
+                "body_entered",
+                owner,
+                "_on_body_entered",
+                VariantArray::new_shared(),
+                1,
+            )
+            .unwrap();
+    }
+
+    #[export]
+    fn _draw(&self, owner: &Area2D) {
+        let color = if self.active {
+            Color::rgb(1.0, 0.8, 0.2)
+        } else {
+            Color::rgba(1.0, 1.0, 1.0, 0.4)
+        };
+
+        owner.draw_circle(Vector2::zero(), 5.0, color);
+    }
+
+    // Accepting signal
+    #[export]
+    fn _on_body_entered(&mut self, owner: &Area2D, body: Ref<Node>) {
+        let body = unsafe { body.assume_safe() };
+        if !body.is_in_group("player") {
+            return;
+        }
+
+        if self.heal {
+            match get_instance::<Stats>(owner, "/root/PlayerStats") {
+                Ok(stats) => unsafe { stats.assume_safe() }
+                    .map_mut(|stats, owner| stats.set_health(&owner, stats.max_health()))
+                    .expect("PlayerStats should not be borrowed"),
+                Err(error) => godot_print!("{}, not healing", error),
+            }
+        }
+
+        if self.active {
+            return;
+        }
+
+        save_game::activate_checkpoint(owner);
+        self.active = true;
+
+        // The previous one, if any, is not active anymore. Deferred, so this one is
+        // no longer borrowed when it refreshes as well
+        let tree = unsafe { owner.get_tree().unwrap().assume_safe() };
+        tree.call_group_flags(
+            SceneTree::GROUP_CALL_DEFERRED,
+            "checkpoints",
+            "refresh",
+            &[],
+        );
+
+        if self.save {
+            save_game::save(owner);
+        }
+
+        owner.emit_signal("activated", &[]);
+    }
+
+    // Reads again whether this is the active checkpoint
+    #[export]
+    fn refresh(&mut self, owner: &Area2D) {
+        self.active = save_game::is_active_checkpoint(owner);
+        owner.update();
+    }
+}
+
+// Position of the active checkpoint, if it is in the current scene
+pub fn active_position(node: &Node) -> Option<Vector2> {
+    let tree = unsafe { node.get_tree()?.assume_safe() };
+    let checkpoints = tree.get_nodes_in_group("checkpoints");
+
+    checkpoints
+        .iter()
+        .filter_map(|checkpoint| checkpoint.try_to_object::<Node2D>())
+        .map(|checkpoint| unsafe { checkpoint.assume_safe() })
+        .find(|checkpoint| save_game::is_active_checkpoint(checkpoint))
+        .map(|checkpoint| checkpoint.global_position())
+}
@@ -0,0 +1,195 @@
+// One swing of an attack combo.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ComboStep {
+    pub damage: i64,
+    // Multiplier applied to the facing direction to get the hitbox `knockback_vector`
+    pub knockback: f32,
+}
+
+// Timing rules of the light1 -> light2 -> finisher chain.
+// A press during a swing is buffered for `buffer_window` seconds and chains into the next
+// step when the swing ends. Pressing again within `chain_window` seconds after a swing
+// ended also continues the chain instead of starting over.
+pub struct ComboChain {
+    steps: Vec<ComboStep>,
+    buffer_window: f32,
+    chain_window: f32,
+
+    // Index of the swing in progress, or of the last one while the chain window is open
+    current: Option<usize>,
+    attacking: bool,
+    buffer_time: f32,
+    idle_time: f32,
+}
+
+impl ComboChain {
+    pub fn new(steps: Vec<ComboStep>, buffer_window: f32, chain_window: f32) -> Self {
+        ComboChain {
+            steps,
+            buffer_window,
+            chain_window,
+
+            current: None,
+            attacking: false,
+            buffer_time: 0.0,
+            idle_time: 0.0,
+        }
+    }
+
+    // Starts a swing from idle and returns it.
+    pub fn start(&mut self) -> ComboStep {
+        let index = self.next_index();
+        self.begin(index)
+    }
+
+    // Remembers a press made while a swing is still playing.
+    pub fn buffer_press(&mut self) {
+        if self.attacking {
+            self.buffer_time = self.buffer_window;
+        }
+    }
+
+    pub fn tick(&mut self, delta: f32) {
+        self.buffer_time = (self.buffer_time - delta).max(0.0);
+
+        if !self.attacking && self.current.is_some() {
+            self.idle_time += delta;
+
+            if self.idle_time > self.chain_window {
+                self.current = None;
+            }
+        }
+    }
+
+    // Ends the current swing. Returns the next step when a buffered press chains into it.
+    pub fn finish(&mut self) -> Option<ComboStep> {
+        if self.buffer_time > 0.0 && self.has_next() {
+            let index = self.next_index();
+            return Some(self.begin(index));
+        }
+
+        self.attacking = false;
+        self.buffer_time = 0.0;
+        self.idle_time = 0.0;
+
+        // The finisher closes the chain
+        if !self.has_next() {
+            self.current = None;
+        }
+
+        None
+    }
+
+    // Drops the chain, e.g. when the player gets hit mid-swing.
+    pub fn cancel(&mut self) {
+        self.current = None;
+        self.attacking = false;
+        self.buffer_time = 0.0;
+        self.idle_time = 0.0;
+    }
+
+    fn has_next(&self) -> bool {
+        match self.current {
+            Some(index) => index + 1 < self.steps.len(),
+            None => true,
+        }
+    }
+
+    fn next_index(&self) -> usize {
+        match self.current {
+            Some(index) if index + 1 < self.steps.len() => index + 1,
+            _ => 0,
+        }
+    }
+
+    fn begin(&mut self, index: usize) -> ComboStep {
+        self.current = Some(index);
+        self.attacking = true;
+        self.buffer_time = 0.0;
+        self.idle_time = 0.0;
+
+        self.steps[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(damage: i64) -> ComboStep {
+        ComboStep {
+            damage,
+            knockback: 1.0,
+        }
+    }
+
+    fn chain() -> ComboChain {
+        ComboChain::new(vec![step(1), step(2), step(3)], 0.2, 0.4)
+    }
+
+    #[test]
+    fn press_inside_buffer_window_chains() {
+        let mut combo = chain();
+        assert_eq!(combo.start(), step(1));
+
+        combo.buffer_press();
+        combo.tick(0.1);
+        assert_eq!(combo.finish(), Some(step(2)));
+    }
+
+    #[test]
+    fn press_outside_buffer_window_is_dropped() {
+        let mut combo = chain();
+        combo.start();
+
+        combo.buffer_press();
+        combo.tick(0.3);
+        assert_eq!(combo.finish(), None);
+    }
+
+    #[test]
+    fn press_within_chain_window_continues() {
+        let mut combo = chain();
+        combo.start();
+        combo.finish();
+
+        combo.tick(0.3);
+        assert_eq!(combo.start(), step(2));
+    }
+
+    #[test]
+    fn press_after_chain_window_starts_over() {
+        let mut combo = chain();
+        combo.start();
+        combo.finish();
+
+        combo.tick(0.5);
+        assert_eq!(combo.start(), step(1));
+    }
+
+    #[test]
+    fn finisher_wraps_to_first_step() {
+        let mut combo = chain();
+        combo.start();
+        combo.buffer_press();
+        assert_eq!(combo.finish(), Some(step(2)));
+        combo.buffer_press();
+        assert_eq!(combo.finish(), Some(step(3)));
+
+        // Nothing chains past the finisher
+        combo.buffer_press();
+        assert_eq!(combo.finish(), None);
+        assert_eq!(combo.start(), step(1));
+    }
+
+    #[test]
+    fn cancel_clears_buffered_press() {
+        let mut combo = chain();
+        combo.start();
+        combo.buffer_press();
+
+        combo.cancel();
+        assert_eq!(combo.finish(), None);
+        assert_eq!(combo.start(), step(1));
+    }
+}
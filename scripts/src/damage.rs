@@ -0,0 +1,277 @@
+use gdnative::api::*;
+use gdnative::prelude::*;
+
+use crate::hitbox::Hitbox;
+use crate::stats::Stats;
+use crate::status::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DamageType {
+    Physical,
+    Fire,
+    Poison,
+}
+
+impl DamageType {
+    // Matches the `damage_type` property exported by `Hitbox`
+    pub fn from_i64(value: i64) -> Self {
+        match value {
+            1 => DamageType::Fire,
+            2 => DamageType::Poison,
+            _ => DamageType::Physical,
+        }
+    }
+
+    pub fn to_i64(self) -> i64 {
+        match self {
+            DamageType::Physical => 0,
+            DamageType::Fire => 1,
+            DamageType::Poison => 2,
+        }
+    }
+}
+
+// A single hit, as built by `Hitbox`.
+#[derive(Clone, Debug)]
+pub struct DamageInfo {
+    pub amount: i64,
+    pub damage_type: DamageType,
+    pub source: Option<Ref<Node>>,
+    pub knockback: Vector2,
+    pub critical: bool,
+    pub status: Option<StatusEffect>,
+}
+
+// Hits cross into GDScript and signals as a `Dictionary`
+impl ToVariant for DamageInfo {
+    fn to_variant(&self) -> Variant {
+        let dictionary = Dictionary::new();
+
+        dictionary.insert("amount", self.amount);
+        dictionary.insert("damage_type", self.damage_type.to_i64());
+        dictionary.insert("source", self.source.clone());
+        dictionary.insert("knockback", self.knockback);
+        dictionary.insert("critical", self.critical);
+
+        if let Some(status) = self.status {
+            dictionary.insert("status", status.kind.to_i64());
+            dictionary.insert("status_duration", status.duration);
+            dictionary.insert("status_tick_damage", status.tick_damage);
+            dictionary.insert("status_tick_interval", status.tick_interval);
+            dictionary.insert("status_magnitude", status.magnitude);
+        }
+
+        dictionary.into_shared().to_variant()
+    }
+}
+
+impl FromVariant for DamageInfo {
+    fn from_variant(variant: &Variant) -> Result<Self, FromVariantError> {
+        let dictionary = Dictionary::from_variant(variant)?;
+
+        Ok(DamageInfo {
+            amount: i64::from_variant(&dictionary.get("amount"))?,
+            damage_type: DamageType::from_i64(i64::from_variant(&dictionary.get("damage_type"))?),
+            source: Option::<Ref<Node>>::from_variant(&dictionary.get("source"))?,
+            knockback: Vector2::from_variant(&dictionary.get("knockback"))?,
+            critical: bool::from_variant(&dictionary.get("critical"))?,
+            status: status_from_dictionary(&dictionary)?,
+        })
+    }
+}
+
+fn status_from_dictionary(
+    dictionary: &Dictionary,
+) -> Result<Option<StatusEffect>, FromVariantError> {
+    if !dictionary.contains("status") {
+        return Ok(None);
+    }
+
+    let kind = match StatusKind::from_i64(i64::from_variant(&dictionary.get("status"))?) {
+        Some(kind) => kind,
+        None => return Ok(None),
+    };
+
+    Ok(Some(StatusEffect {
+        kind,
+        duration: f32::from_variant(&dictionary.get("status_duration"))?,
+        tick_damage: i64::from_variant(&dictionary.get("status_tick_damage"))?,
+        tick_interval: f32::from_variant(&dictionary.get("status_tick_interval"))?,
+        magnitude: f32::from_variant(&dictionary.get("status_magnitude"))?,
+    }))
+}
+
+// Fraction of each damage type that is ignored, from 0.0 (none) to 1.0 (immune),
+// plus flat `defense` taken off physical hits afterwards.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Resistances {
+    pub physical: f64,
+    pub fire: f64,
+    pub poison: f64,
+    pub defense: f64,
+}
+
+impl Resistances {
+    pub fn against(&self, damage_type: DamageType) -> f64 {
+        match damage_type {
+            DamageType::Physical => self.physical,
+            DamageType::Fire => self.fire,
+            DamageType::Poison => self.poison,
+        }
+    }
+}
+
+// Amount of `damage` left after `resistances` are applied.
+pub fn resolve_damage(damage: &DamageInfo, resistances: &Resistances) -> i64 {
+    let resistance = num::clamp(resistances.against(damage.damage_type), 0.0, 1.0);
+    let mut amount = damage.amount as f64 * (1.0 - resistance);
+
+    if damage.damage_type == DamageType::Physical {
+        amount -= resistances.defense.max(0.0);
+    }
+
+    (amount.round() as i64).max(0)
+}
+
+// Reads the hit carried by a `Hitbox` area and applies it to a `Stats` node.
+// Returns the hit with its resolved amount, or `None` if either node has the wrong script.
+pub fn apply_hit(area: Ref<Area2D>, stats: Ref<Node>) -> Option<DamageInfo> {
+    let area = unsafe { area.assume_safe() };
+    let hitbox = area.cast_instance::<Hitbox>()?;
+    let damage = hitbox.map(|hitbox, owner| hitbox.damage_info(owner)).ok()?;
+
+    let stats = unsafe { stats.assume_safe() };
+    let stats = stats.cast_instance::<Stats>()?;
+
+    stats
+        .map_mut(|stats, owner| stats.take_damage(&owner, damage))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(amount: i64, damage_type: DamageType) -> DamageInfo {
+        DamageInfo {
+            amount,
+            damage_type,
+            source: None,
+            knockback: Vector2::zero(),
+            critical: false,
+            status: None,
+        }
+    }
+
+    #[test]
+    fn resistance_scales_its_damage_type() {
+        let resistances = Resistances {
+            fire: 0.5,
+            ..Resistances::default()
+        };
+
+        assert_eq!(resolve_damage(&hit(10, DamageType::Fire), &resistances), 5);
+        assert_eq!(
+            resolve_damage(&hit(10, DamageType::Poison), &resistances),
+            10
+        );
+        assert_eq!(
+            resolve_damage(&hit(10, DamageType::Physical), &resistances),
+            10
+        );
+    }
+
+    #[test]
+    fn resistance_is_kept_between_none_and_immune() {
+        let resistances = Resistances {
+            physical: -1.0,
+            fire: 2.0,
+            ..Resistances::default()
+        };
+
+        assert_eq!(
+            resolve_damage(&hit(10, DamageType::Physical), &resistances),
+            10
+        );
+        assert_eq!(resolve_damage(&hit(10, DamageType::Fire), &resistances), 0);
+    }
+
+    #[test]
+    fn defense_is_taken_off_physical_hits_after_resistance() {
+        let resistances = Resistances {
+            physical: 0.5,
+            fire: 0.5,
+            defense: 2.0,
+            ..Resistances::default()
+        };
+
+        assert_eq!(
+            resolve_damage(&hit(10, DamageType::Physical), &resistances),
+            3
+        );
+        assert_eq!(resolve_damage(&hit(10, DamageType::Fire), &resistances), 5);
+    }
+
+    #[test]
+    fn damage_never_goes_below_zero() {
+        let resistances = Resistances {
+            defense: 5.0,
+            ..Resistances::default()
+        };
+
+        assert_eq!(
+            resolve_damage(&hit(3, DamageType::Physical), &resistances),
+            0
+        );
+    }
+
+    #[test]
+    fn negative_defense_adds_nothing() {
+        let resistances = Resistances {
+            defense: -3.0,
+            ..Resistances::default()
+        };
+
+        assert_eq!(
+            resolve_damage(&hit(4, DamageType::Physical), &resistances),
+            4
+        );
+    }
+
+    #[test]
+    fn damage_type_survives_its_number() {
+        for damage_type in [DamageType::Physical, DamageType::Fire, DamageType::Poison].iter() {
+            assert_eq!(DamageType::from_i64(damage_type.to_i64()), *damage_type);
+        }
+        assert_eq!(DamageType::from_i64(7), DamageType::Physical);
+    }
+
+    // `Dictionary` is built by the engine, so this one only runs where the Godot API is loaded
+    #[test]
+    #[ignore]
+    fn damage_info_survives_the_dictionary() {
+        let damage = DamageInfo {
+            amount: 3,
+            damage_type: DamageType::Fire,
+            source: None,
+            knockback: Vector2::new(1.0, -2.0),
+            critical: true,
+            status: Some(StatusEffect {
+                kind: StatusKind::Burn,
+                duration: 2.0,
+                tick_damage: 1,
+                tick_interval: 0.5,
+                magnitude: 0.0,
+            }),
+        };
+
+        let back = DamageInfo::from_variant(&damage.to_variant()).unwrap();
+
+        assert_eq!(back.amount, damage.amount);
+        assert_eq!(back.damage_type, damage.damage_type);
+        assert!(back.source.is_none());
+        assert_eq!(back.knockback, damage.knockback);
+        assert_eq!(back.critical, damage.critical);
+        assert_eq!(back.status, damage.status);
+    }
+}
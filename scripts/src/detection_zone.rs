@@ -0,0 +1,167 @@
+use gdnative::api::*;
+use gdnative::prelude::*;
+
+// DetectionZone "class".
+// Keeps track of the bodies of `target_group` inside the zone, e.g. "player".
+// Being inside is not enough to be seen: nothing on `sight_mask` may be in between.
+#[derive(NativeClass)]
+#[inherit(Area2D)]
+#[register_with(Self::register_signals)]
+pub struct DetectionZone {
+    // Group, used as faction, of the bodies to track
+    #[property]
+    target_group: String,
+    // Layers that block line of sight
+    #[property(default = 1)]
+    sight_mask: i64,
+
+    targets: Vec<Ref<Node>>,
+}
+
+#[gdnative::methods]
+impl DetectionZone {
+    pub fn new(_owner: &Area2D) -> Self {
+        DetectionZone {
+            target_group: "player".to_string(),
+            sight_mask: 1,
+
+            targets: Vec::new(),
+        }
+    }
+
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder.add_signal(Signal {
+            name: "target_acquired",
+            args: &[SignalArgument {
+                name: "target",
+                default: Variant::new(),
+                export_info: ExportInfo::new(VariantType::Object),
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
+
+        builder.add_signal(Signal {
+            name: "target_lost",
+            args: &[SignalArgument {
+                name: "target",
+                default: Variant::new(),
+                export_info: ExportInfo::new(VariantType::Object),
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
+    }
+
+    #[export]
+    fn _ready(&self, owner: TRef<Area2D>) {
+        // Not deferred, a body leaving because it was freed must be dropped right away
+        owner
+            .connect(
+                "body_entered",
+                owner,
+                "_on_body_entered",
+                VariantArray::new_shared(),
+                0,
+            )
+            .unwrap();
+
+        owner
+            .connect(
+                "body_exited",
+                owner,
+                "_on_body_exited",
+                VariantArray::new_shared(),
+                0,
+            )
+            .unwrap();
+    }
+
+    #[export]
+    fn _on_body_entered(&mut self, owner: &Area2D, body: Ref<Node>) {
+        let target = unsafe { body.assume_safe() };
+        if !target.is_in_group(self.target_group.as_str()) || self.targets.contains(&body) {
+            return;
+        }
+
+        self.targets.push(body);
+        owner.emit_signal("target_acquired", &[body.to_variant()]);
+    }
+
+    #[export]
+    fn _on_body_exited(&mut self, owner: &Area2D, body: Ref<Node>) {
+        if !self.targets.contains(&body) {
+            return;
+        }
+
+        self.targets.retain(|target| *target != body);
+        owner.emit_signal("target_lost", &[body.to_variant()]);
+    }
+
+    // Whether `target` is in the zone with nothing in between
+    #[export]
+    fn can_see_target(&self, owner: &Area2D, target: Ref<Node>) -> bool {
+        self.targets.contains(&target) && self.visible_position(owner, target).is_some()
+    }
+
+    // Closest target that can be seen, null if there is none
+    #[export]
+    fn nearest_target(&self, owner: &Area2D) -> Option<Ref<Node>> {
+        self.nearest_visible(owner).map(|(target, _)| target)
+    }
+
+    // Every target in the zone, seen or not
+    #[export]
+    fn targets(&self, _owner: &Area2D) -> VariantArray {
+        let targets = VariantArray::new();
+
+        for target in self.targets.iter() {
+            targets.push(*target);
+        }
+
+        targets.into_shared()
+    }
+}
+
+impl DetectionZone {
+    // Closest target that can be seen, with its position
+    pub fn nearest_visible(&self, owner: &Area2D) -> Option<(Ref<Node>, Vector2)> {
+        let origin = owner.global_position();
+
+        self.targets
+            .iter()
+            .filter_map(|target| {
+                self.visible_position(owner, *target)
+                    .map(|position| (*target, position))
+            })
+            .min_by(|(_, a), (_, b)| {
+                let a = origin.distance_squared_to(*a);
+                let b = origin.distance_squared_to(*b);
+                a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+            })
+    }
+
+    // Global position of `target` if nothing blocks the view of it
+    fn visible_position(&self, owner: &Area2D, target: Ref<Node>) -> Option<Vector2> {
+        let target = unsafe { target.assume_safe_if_sane() }?;
+        let position = target.cast::<Node2D>()?.global_position();
+
+        let world = owner.get_world_2d()?;
+        let world = unsafe { world.assume_safe() };
+        let space_state = world.direct_space_state()?;
+        let space_state = unsafe { space_state.assume_safe() };
+
+        let hit = space_state.intersect_ray(
+            owner.global_position(),
+            position,
+            VariantArray::new_shared(),
+            self.sight_mask,
+            true,
+            false,
+        );
+
+        if hit.is_empty() {
+            Some(position)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use gdnative::api::*;
+use gdnative::prelude::*;
+
+use crate::scene_manager::{self, Spawn};
+
+// Door "class".
+// Sends the player walking in to the `spawn_point` of `target_scene` through the
+// `SceneManager` fade. Without a `target_scene` it is a warp inside the current one.
+#[derive(NativeClass)]
+#[inherit(Area2D)]
+pub struct Door {
+    // e.g. "res://World/Clearing.tscn"
+    #[property]
+    target_scene: String,
+    // Name of a node in the "spawn_points" group of the target scene
+    #[property]
+    spawn_point: String,
+}
+
+#[gdnative::methods]
+impl Door {
+    // The "constructor" of the class.
+    fn new(_owner: &Area2D) -> Self {
+        Door {
+            target_scene: String::new(),
+            spawn_point: String::new(),
+        }
+    }
+
+    #[export]
+    fn _ready(&self, owner: TRef<Area2D>) {
+        owner
+            .connect(
+                "body_entered",
+                owner,
+                "_on_body_entered",
+                VariantArray::new_shared(),
+                1,
+            )
+            .unwrap();
+    }
+
+    // Accepting signal
+    #[export]
+    fn _on_body_entered(&self, owner: &Area2D, body: Ref<Node>) {
+        let body = unsafe { body.assume_safe() };
+        if !body.is_in_group("player") {
+            return;
+        }
+
+        let scene = if self.target_scene.is_empty() {
+            None
+        } else {
+            Some(self.target_scene.clone())
+        };
+
+        scene_manager::transition(owner, scene, Spawn::Point(self.spawn_point.clone()));
+    }
+}
@@ -0,0 +1,165 @@
+use gdnative::api::*;
+use gdnative::prelude::*;
+
+use crate::utils::{disable, get_ref, get_typed, play_sound, NodeError};
+
+#[derive(Clone, Copy)]
+enum GameOverOption {
+    Retry,
+    Quit,
+}
+
+// In the same order as the labels under `Options`
+const OPTIONS: [GameOverOption; 2] = [GameOverOption::Retry, GameOverOption::Quit];
+
+// GameOver "class".
+// Shows up `delay` seconds after `PlayerStats` runs out of health and pauses the
+// scene tree. Retry respawns the player, Quit closes the game.
+#[derive(NativeClass)]
+#[inherit(Control)]
+pub struct GameOver {
+    // Seconds left for the death animation before the overlay shows
+    #[property(default = 1.0)]
+    delay: f64,
+
+    show_in: Option<f64>,
+    selected: usize,
+    option_labels: Vec<Ref<Label>>,
+    move_sound: Ref<AudioStreamPlayer>,
+    select_sound: Ref<AudioStreamPlayer>,
+}
+
+#[gdnative::methods]
+impl GameOver {
+    // The "constructor" of the class.
+    fn new(_owner: &Control) -> Self {
+        GameOver {
+            delay: 1.0,
+
+            show_in: None,
+            selected: 0,
+            option_labels: Vec::new(),
+            move_sound: AudioStreamPlayer::new().into_shared(),
+            select_sound: AudioStreamPlayer::new().into_shared(),
+        }
+    }
+
+    #[export]
+    fn _ready(&mut self, owner: TRef<Control>) {
+        owner.set_visible(false);
+
+        if let Err(error) = self.find_nodes(&owner) {
+            disable(&owner, error);
+            return;
+        }
+
+        let stats = match get_typed::<Node>(&owner, "/root/PlayerStats") {
+            Ok(stats) => stats,
+            Err(error) => {
+                disable(&owner, error);
+                return;
+            }
+        };
+
+        stats
+            .connect(
+                "no_health",
+                owner,
+                "_on_player_stats_no_health",
+                VariantArray::new_shared(),
+                1,
+            )
+            .unwrap();
+    }
+
+    #[export]
+    fn _process(&mut self, owner: &Control, delta: f64) {
+        if let Some(show_in) = self.show_in {
+            if show_in - delta > 0.0 {
+                self.show_in = Some(show_in - delta);
+            } else {
+                self.show_in = None;
+                self.open(owner);
+            }
+            return;
+        }
+
+        if !owner.is_visible() {
+            return;
+        }
+
+        let input = Input::godot_singleton();
+
+        if Input::is_action_just_pressed(input, "ui_up")
+            || Input::is_action_just_pressed(input, "ui_down")
+        {
+            // Only two options, up and down both switch
+            self.selected = (self.selected + 1) % OPTIONS.len();
+
+            self.update_labels();
+            play_sound(&self.move_sound);
+        } else if Input::is_action_just_pressed(input, "ui_accept") {
+            self.select(owner);
+        }
+    }
+
+    // Accepting signal from the `PlayerStats` singleton
+    #[export]
+    fn _on_player_stats_no_health(&mut self, owner: &Control) {
+        if self.show_in.is_none() && !owner.is_visible() {
+            self.show_in = Some(self.delay);
+        }
+    }
+}
+
+impl GameOver {
+    fn find_nodes(&mut self, owner: &Control) -> Result<(), NodeError> {
+        self.option_labels = ["Options/Retry", "Options/Quit"]
+            .iter()
+            .map(|path| get_ref::<Label>(owner, path))
+            .collect::<Result<_, _>>()?;
+
+        self.move_sound = get_ref::<AudioStreamPlayer>(owner, "MoveSound")?;
+        self.select_sound = get_ref::<AudioStreamPlayer>(owner, "SelectSound")?;
+
+        Ok(())
+    }
+
+    fn set_paused(&self, owner: &Control, paused: bool) {
+        unsafe { owner.get_tree().unwrap().assume_safe().set_pause(paused) };
+    }
+
+    fn open(&mut self, owner: &Control) {
+        self.set_paused(owner, true);
+        owner.set_visible(true);
+
+        self.selected = 0;
+        self.update_labels();
+    }
+
+    fn select(&mut self, owner: &Control) {
+        play_sound(&self.select_sound);
+
+        match OPTIONS[self.selected] {
+            GameOverOption::Retry => {
+                self.set_paused(owner, false);
+                owner.set_visible(false);
+
+                let tree = unsafe { owner.get_tree().unwrap().assume_safe() };
+                tree.call_group("player", "respawn", &[]);
+            }
+            // Leaves without saving, the save from before the death stays
+            GameOverOption::Quit => unsafe { owner.get_tree().unwrap().assume_safe().quit(0) },
+        }
+    }
+
+    // Dims every option but the selected one
+    fn update_labels(&self) {
+        for (index, label) in self.option_labels.iter().enumerate() {
+            let label = unsafe { label.assume_safe() };
+
+            let alpha = if index == self.selected { 1.0 } else { 0.5 };
+            label.set_modulate(Color::rgba(1.0, 1.0, 1.0, alpha));
+        }
+    }
+}
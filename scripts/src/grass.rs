@@ -1,7 +1,8 @@
 use gdnative::api::*;
 use gdnative::prelude::*;
 
-use crate::utils::load_scene;
+use crate::save_game;
+use crate::utils::{get_typed, load_scene};
 
 // Grass "class".
 #[derive(NativeClass)]
@@ -20,7 +21,13 @@ impl Grass {
     }
 
     #[export]
-    fn _ready(&mut self, _owner: &Node2D) {
+    fn _ready(&mut self, owner: &Node2D) {
+        // Already cut in the saved game
+        if save_game::is_consumed(owner) {
+            owner.queue_free();
+            return;
+        }
+
         // Loading effect scene
         let effect_scene_load = load_scene("res://Effects/GrassEffect.tscn");
         match effect_scene_load {
@@ -33,6 +40,15 @@ impl Grass {
     #[export]
     fn _on_hurtbox_area_entered(&mut self, owner: &Node2D, _area: Ref<Area2D>) {
         self.create_grass_effect(owner);
+        save_game::mark_consumed(owner);
+
+        // Cut all the same without loot
+        match get_typed::<Node>(owner, "LootDropper") {
+            Ok(loot_dropper) => unsafe {
+                loot_dropper.call("drop_loot", &[owner.global_position().to_variant()]);
+            },
+            Err(error) => godot_print!("{}", error),
+        }
 
         // Deleting Grass node
         owner.queue_free();
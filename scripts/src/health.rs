@@ -0,0 +1,138 @@
+use gdnative::api::*;
+use gdnative::prelude::*;
+
+use crate::damage::*;
+use crate::utils::{get_ref, load_scene, NodeError};
+
+// Health "class".
+// Sits next to a `Hurtbox` and owns the whole hurt reaction: damage to `Stats`,
+// invincibility, hit effect and sound. The entity only listens to `damaged`.
+#[derive(NativeClass)]
+#[inherit(Node)]
+#[register_with(Self::register_signals)]
+pub struct Health {
+    #[property]
+    stats_path: String,
+    #[property]
+    hurtbox_path: String,
+    #[property(default = 0.4)]
+    invincibility_duration: f64,
+    // Scene instanced in the current scene on every hit, e.g. res://Player/PlayerHurtSound.tscn
+    #[property]
+    hurt_sound_scene: String,
+
+    stats: Ref<Node>,
+    hurtbox: Ref<Area2D>,
+    hurt_sound_load: Option<Ref<PackedScene>>,
+}
+
+#[gdnative::methods]
+impl Health {
+    fn new(_owner: &Node) -> Self {
+        Health {
+            stats_path: "../Stats".to_string(),
+            hurtbox_path: "../Hurtbox".to_string(),
+            invincibility_duration: 0.4,
+            hurt_sound_scene: String::new(),
+
+            stats: Node::new().into_shared(),
+            hurtbox: Area2D::new().into_shared(),
+            hurt_sound_load: None,
+        }
+    }
+
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder.add_signal(Signal {
+            name: "damaged",
+            args: &[SignalArgument {
+                name: "damage",
+                default: Variant::new(),
+                export_info: ExportInfo::new(VariantType::Dictionary),
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
+    }
+
+    #[export]
+    fn _ready(&mut self, owner: TRef<Node>) {
+        // Left unconnected, so the entity can't be hurt
+        if let Err(error) = self.find_nodes(&owner) {
+            godot_print!("{}", error);
+            return;
+        }
+
+        let hurtbox = unsafe { self.hurtbox.assume_safe() };
+        hurtbox
+            .connect(
+                "area_entered",
+                owner,
+                "_on_hurtbox_area_entered",
+                VariantArray::new_shared(),
+                1,
+            )
+            .unwrap();
+
+        // Loading scene
+        if !self.hurt_sound_scene.is_empty() {
+            self.hurt_sound_load = load_scene(&self.hurt_sound_scene);
+
+            if self.hurt_sound_load.is_none() {
+                godot_print!("Could not load hurt sound scene. Check name.");
+            }
+        }
+    }
+
+    // Accepting signal
+    #[export]
+    fn _on_hurtbox_area_entered(&mut self, owner: &Node, area: Ref<Area2D>) {
+        let damage = match apply_hit(area, self.stats.clone()) {
+            Some(damage) => damage,
+            None => return,
+        };
+
+        let hurtbox = unsafe { self.hurtbox.assume_safe() };
+        unsafe { hurtbox.call("create_hit_effect", &[]) };
+        unsafe {
+            hurtbox.call(
+                "start_invincibility",
+                &[self.invincibility_duration.to_variant()],
+            )
+        };
+
+        self.play_hurt_sound(owner);
+
+        owner.emit_signal("damaged", &[damage.to_variant()]);
+    }
+}
+
+impl Health {
+    fn find_nodes(&mut self, owner: &Node) -> Result<(), NodeError> {
+        self.stats = get_ref::<Node>(owner, &self.stats_path)?;
+        self.hurtbox = get_ref::<Area2D>(owner, &self.hurtbox_path)?;
+
+        Ok(())
+    }
+
+    fn play_hurt_sound(&self, owner: &Node) {
+        let hurt_sound_load = match &self.hurt_sound_load {
+            Some(scene) => scene,
+            None => return,
+        };
+
+        let hurt_sound = unsafe { hurt_sound_load.assume_safe() };
+        let hurt_sound = hurt_sound
+            .instance(PackedScene::GEN_EDIT_STATE_DISABLED)
+            .expect("should be able to instance scene");
+
+        unsafe {
+            owner
+                .get_tree()
+                .unwrap()
+                .assume_safe()
+                .current_scene()
+                .unwrap()
+                .assume_safe()
+                .add_child(hurt_sound, false)
+        };
+    }
+}
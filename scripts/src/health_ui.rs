@@ -1,6 +1,18 @@
 use gdnative::api::*;
 use gdnative::prelude::*;
 
+use crate::utils::{get_ref, get_typed, NodeError};
+
+// Signals of `PlayerStats` and the setter each one drives
+const STATS_SIGNALS: [(&str, &str); 6] = [
+    ("health_changed", "set_hearts"),
+    ("max_health_changed", "set_max_hearts"),
+    ("stamina_changed", "set_stamina"),
+    ("max_stamina_changed", "set_max_stamina"),
+    ("xp_changed", "set_xp"),
+    ("leveled_up", "set_level"),
+];
+
 // HealthUI "class".
 #[derive(NativeClass)]
 #[inherit(Control)]
@@ -9,8 +21,12 @@ pub struct HealthUI {
     hearts: i64,
     #[property(default = 1)]
     max_hearts: i64,
-    heart_ui_full: Ref<Node>,
-    heart_ui_empty: Ref<Node>,
+    heart_ui_full: Ref<TextureRect>,
+    heart_ui_empty: Ref<TextureRect>,
+    stamina_bar: Ref<ProgressBar>,
+    coin_label: Ref<Label>,
+    level_label: Ref<Label>,
+    xp_bar: Ref<ProgressBar>,
 }
 
 #[gdnative::methods]
@@ -19,61 +35,73 @@ impl HealthUI {
         HealthUI {
             hearts: 4,
             max_hearts: 4,
-            heart_ui_full: Node::new().into_shared(),
-            heart_ui_empty: Node::new().into_shared(),
+            heart_ui_full: TextureRect::new().into_shared(),
+            heart_ui_empty: TextureRect::new().into_shared(),
+            stamina_bar: ProgressBar::new().into_shared(),
+            coin_label: Label::new().into_shared(),
+            level_label: Label::new().into_shared(),
+            xp_bar: ProgressBar::new().into_shared(),
         }
     }
 
     #[export]
     fn _ready(&mut self, owner: TRef<Control>) {
-        self.heart_ui_full = owner
-            .get_node("HeartUIFull")
-            .expect("Label node should exist");
-
-        self.heart_ui_empty = owner
-            .get_node("HeartUIEmpty")
-            .expect("Label node should exist");
+        // Hidden rather than showing stale hearts
+        if let Err(error) = self.find_nodes(&owner) {
+            godot_print!("{}", error);
+            owner.set_visible(false);
+            return;
+        }
 
         // Access `PlayerStats` singleton
-        let player_stats = owner
-            .get_node("../../../PlayerStats")
-            .expect("PlayerStats node Should Exist");
-        let player_stats = unsafe { player_stats.assume_safe() };
+        let player_stats = match get_typed::<Node>(&owner, "/root/PlayerStats") {
+            Ok(player_stats) => player_stats,
+            Err(error) => {
+                godot_print!("{}", error);
+                owner.set_visible(false);
+                return;
+            }
+        };
 
         self.set_max_hearts(&owner, player_stats.get("max_health").to_i64());
         self.set_hearts(&owner, player_stats.get("health").to_i64());
+        self.set_max_stamina(&owner, player_stats.get("max_stamina").to_f64());
+        self.set_stamina(&owner, player_stats.get("stamina").to_f64());
+        self.set_level(
+            &owner,
+            unsafe { player_stats.call("get_level", &[]) }.to_i64(),
+        );
+        self.set_xp(
+            &owner,
+            unsafe { player_stats.call("get_xp", &[]) }.to_i64(),
+            unsafe { player_stats.call("get_xp_to_next", &[]) }.to_i64(),
+        );
         // self.set_hearts(
         //     &owner,
         //     unsafe { player_stats.call("get_health", &[]) }.to_i64(),
         // );
 
-        player_stats
-            .connect(
-                "health_changed",
-                owner,
-                "set_hearts",
-                VariantArray::new_shared(),
-                1,
-            )
-            .unwrap();
-
-        player_stats
-            .connect(
-                "max_health_changed",
-                owner,
-                "set_max_hearts",
-                VariantArray::new_shared(),
-                1,
-            )
-            .unwrap();
+        for (signal, method) in STATS_SIGNALS.iter() {
+            connect(owner, player_stats, signal, method);
+        }
+
+        // Access `Inventory` singleton, the coin counter stays at 0 without it
+        let inventory = match get_typed::<Node>(&owner, "/root/Inventory") {
+            Ok(inventory) => inventory,
+            Err(error) => {
+                godot_print!("{}", error);
+                return;
+            }
+        };
+
+        self.set_coins(&owner, unsafe { inventory.call("get_coins", &[]) }.to_i64());
+
+        connect(owner, inventory, "coins_changed", "set_coins");
     }
 
     #[export]
     fn set_hearts(&mut self, _owner: &Control, value: i64) {
         let health_ui_full = unsafe { self.heart_ui_full.assume_safe() };
-        let health_ui_full = health_ui_full
-            .cast::<TextureRect>()
-            .expect("Node should cast to TextureRect");
 
         health_ui_full.set_size(Vector2::new(value as f32 * 15.0, 11.0), false);
     }
@@ -81,10 +109,74 @@ impl HealthUI {
     #[export]
     fn set_max_hearts(&mut self, _owner: &Control, value: i64) {
         let health_ui_empty = unsafe { self.heart_ui_empty.assume_safe() };
-        let health_ui_empty = health_ui_empty
-            .cast::<TextureRect>()
-            .expect("Node should cast to TextureRect");
 
         health_ui_empty.set_size(Vector2::new(value as f32 * 15.0, 11.0), false);
     }
+
+    #[export]
+    fn set_stamina(&mut self, _owner: &Control, value: f64) {
+        let stamina_bar = unsafe { self.stamina_bar.assume_safe() };
+
+        stamina_bar.set_value(value);
+    }
+
+    #[export]
+    fn set_max_stamina(&mut self, _owner: &Control, value: f64) {
+        let stamina_bar = unsafe { self.stamina_bar.assume_safe() };
+
+        stamina_bar.set_max(value);
+    }
+
+    #[export]
+    fn set_coins(&mut self, _owner: &Control, value: i64) {
+        let coin_label = unsafe { self.coin_label.assume_safe() };
+
+        coin_label.set_text(value.to_string());
+    }
+
+    #[export]
+    fn set_level(&mut self, _owner: &Control, value: i64) {
+        let level_label = unsafe { self.level_label.assume_safe() };
+
+        level_label.set_text(format!("Lv {}", value));
+    }
+
+    // `xp_to_next` is 0 at the max level, shown as a full bar
+    #[export]
+    fn set_xp(&mut self, _owner: &Control, xp: i64, xp_to_next: i64) {
+        let xp_bar = unsafe { self.xp_bar.assume_safe() };
+
+        if xp_to_next > 0 {
+            xp_bar.set_max(xp_to_next as f64);
+            xp_bar.set_value(xp as f64);
+        } else {
+            xp_bar.set_max(1.0);
+            xp_bar.set_value(1.0);
+        }
+    }
+}
+
+impl HealthUI {
+    fn find_nodes(&mut self, owner: &Control) -> Result<(), NodeError> {
+        self.heart_ui_full = get_ref::<TextureRect>(owner, "HeartUIFull")?;
+        self.heart_ui_empty = get_ref::<TextureRect>(owner, "HeartUIEmpty")?;
+        self.stamina_bar = get_ref::<ProgressBar>(owner, "StaminaBar")?;
+        self.coin_label = get_ref::<Label>(owner, "CoinLabel")?;
+        self.level_label = get_ref::<Label>(owner, "LevelLabel")?;
+        self.xp_bar = get_ref::<ProgressBar>(owner, "XPBar")?;
+
+        Ok(())
+    }
+}
+
+// A signal that can't be connected leaves its part of the UI as it is
+fn connect(owner: TRef<Control>, source: TRef<Node>, signal: &str, method: &str) {
+    if let Err(error) = source.connect(signal, owner, method, VariantArray::new_shared(), 1) {
+        godot_print!(
+            "HealthUI: could not connect {} to {}: {:?}",
+            signal,
+            method,
+            error
+        );
+    }
 }
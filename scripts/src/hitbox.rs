@@ -1,5 +1,12 @@
 use gdnative::api::*;
 use gdnative::prelude::*;
+use rand::Rng;
+
+use crate::damage::*;
+use crate::modifiers::StatKind;
+use crate::rng_service::with_rng;
+use crate::stats::Stats;
+use crate::status::*;
 
 // Hitbox "class".
 #[derive(NativeClass)]
@@ -9,6 +16,29 @@ pub struct Hitbox {
     knockback_vector: Vector2,
     #[property(default = 1)]
     pub damage: i64,
+    // 0 = physical, 1 = fire, 2 = poison
+    #[property(default = 0)]
+    damage_type: i64,
+    #[property(default = 0.0)]
+    crit_chance: f64,
+    #[property(default = 2.0)]
+    crit_multiplier: f64,
+    // -1 = none, 0 = poison, 1 = burn, 2 = slow, 3 = stun
+    #[property(path = "status/kind", default = -1)]
+    status_kind: i64,
+    #[property(path = "status/duration", default = 3.0)]
+    status_duration: f32,
+    #[property(path = "status/tick_damage", default = 1)]
+    status_tick_damage: i64,
+    #[property(path = "status/tick_interval", default = 1.0)]
+    status_tick_interval: f32,
+    #[property(path = "status/magnitude", default = 0.5)]
+    status_magnitude: f32,
+    // `Stats` of the attacker, whose attack and crit chance are added to every hit
+    #[property]
+    stats_path: String,
+
+    stats: Option<Ref<Node>>,
 }
 
 #[gdnative::methods]
@@ -17,6 +47,29 @@ impl Hitbox {
         Hitbox {
             knockback_vector: Vector2::zero(),
             damage: 1,
+            damage_type: 0,
+            crit_chance: 0.0,
+            crit_multiplier: 2.0,
+            status_kind: -1,
+            status_duration: 3.0,
+            status_tick_damage: 1,
+            status_tick_interval: 1.0,
+            status_magnitude: 0.5,
+            stats_path: String::new(),
+
+            stats: None,
+        }
+    }
+
+    #[export]
+    fn _ready(&mut self, owner: &Area2D) {
+        // Access to the attacker `Stats` node
+        if !self.stats_path.is_empty() {
+            self.stats = owner.get_node(self.stats_path.as_str());
+
+            if self.stats.is_none() {
+                godot_print!("Hitbox stats not found at {}", self.stats_path);
+            }
         }
     }
 
@@ -25,3 +78,59 @@ impl Hitbox {
         self.damage
     }
 }
+
+impl Hitbox {
+    // Builds the hit this hitbox deals right now
+    pub fn damage_info(&self, owner: TRef<Area2D>) -> DamageInfo {
+        let (attack, crit_chance) = self.attacker_bonus();
+        let damage = self.damage as f64 + attack;
+
+        let crit_chance = num::clamp(self.crit_chance + crit_chance, 0.0, 1.0);
+        let critical = with_rng(&owner, "crit", |rng| rng.gen_bool(crit_chance));
+
+        let amount = if critical {
+            damage * self.crit_multiplier
+        } else {
+            damage
+        };
+        let amount = (amount.round() as i64).max(0);
+
+        DamageInfo {
+            amount,
+            damage_type: DamageType::from_i64(self.damage_type),
+            // The scene root the hitbox belongs to, e.g. `Player` for the sword
+            source: owner.owner(),
+            knockback: self.knockback_vector,
+            critical,
+            status: StatusKind::from_i64(self.status_kind).map(|kind| StatusEffect {
+                kind,
+                duration: self.status_duration,
+                tick_damage: self.status_tick_damage,
+                tick_interval: self.status_tick_interval,
+                magnitude: self.status_magnitude,
+            }),
+        }
+    }
+
+    // Attack and crit chance from the attacker `Stats`, zero without one
+    fn attacker_bonus(&self) -> (f64, f64) {
+        let stats = match &self.stats {
+            Some(stats) => unsafe { stats.assume_safe() },
+            None => return (0.0, 0.0),
+        };
+
+        stats
+            .cast_instance::<Stats>()
+            .and_then(|stats| {
+                stats
+                    .map(|stats, _| {
+                        (
+                            stats.stat_value(StatKind::Attack),
+                            stats.stat_value(StatKind::CritChance),
+                        )
+                    })
+                    .ok()
+            })
+            .unwrap_or((0.0, 0.0))
+    }
+}
@@ -1,7 +1,7 @@
 use gdnative::api::*;
 use gdnative::prelude::*;
 
-use crate::utils::load_scene;
+use crate::utils::{get_ref, load_scene, NodeError};
 
 // Hurtbox "class".
 #[derive(NativeClass)]
@@ -12,8 +12,8 @@ pub struct Hurtbox {
     scene_tree: Ref<SceneTree>,
     main: Ref<Node>,
     invincible: bool,
-    timer: Ref<Node>,
-    collision_shape: Ref<Node>,
+    timer: Ref<Timer>,
+    collision_shape: Ref<CollisionShape2D>,
 }
 
 // Hurtbox Implementation
@@ -26,8 +26,8 @@ impl Hurtbox {
             scene_tree: SceneTree::new().into_shared(),
             main: Node::new().into_shared(),
             invincible: false,
-            timer: Node::new().into_shared(),
-            collision_shape: Node::new().into_shared(),
+            timer: Timer::new().into_shared(),
+            collision_shape: CollisionShape2D::new().into_shared(),
         }
     }
 
@@ -65,13 +65,11 @@ impl Hurtbox {
             None => godot_print!("Could not get scene node."),
         }
 
-        //Accesing Timer node
-        self.timer = owner.get_node("Timer").expect("Timer node should exist");
-
-        //Accessing CollisionShape2D node
-        self.collision_shape = owner
-            .get_node("CollisionShape2D")
-            .expect("CollisionShape2D node should exist");
+        // A hurtbox that can't turn invincible takes no hits at all
+        if let Err(error) = self.find_nodes(owner) {
+            godot_print!("{}", error);
+            owner.set_deferred("monitoring", false.to_variant());
+        }
     }
 
     #[export]
@@ -109,7 +107,6 @@ impl Hurtbox {
 
         let duration = duration.to_f64();
         let timer = unsafe { self.timer.assume_safe() };
-        let timer = timer.cast::<Timer>().unwrap();
 
         timer.start(duration);
     }
@@ -144,7 +141,6 @@ impl Hurtbox {
     #[export]
     fn _on_hurtbox_invincibility_started(&self, _owner: &Area2D) {
         let collision_shape = unsafe { self.collision_shape.assume_safe() };
-        let collision_shape = collision_shape.cast::<CollisionShape2D>().unwrap();
 
         collision_shape.set_deferred("disabled", true.to_variant());
     }
@@ -152,8 +148,19 @@ impl Hurtbox {
     #[export]
     fn _on_hurtbox_invincibility_ended(&mut self, _owner: &Area2D) {
         let collision_shape = unsafe { self.collision_shape.assume_safe() };
-        let collision_shape = collision_shape.cast::<CollisionShape2D>().unwrap();
 
         collision_shape.set("disabled", false.to_variant());
     }
 }
+
+impl Hurtbox {
+    fn find_nodes(&mut self, owner: &Area2D) -> Result<(), NodeError> {
+        //Accesing Timer node
+        self.timer = get_ref::<Timer>(owner, "Timer")?;
+
+        //Accessing CollisionShape2D node
+        self.collision_shape = get_ref::<CollisionShape2D>(owner, "CollisionShape2D")?;
+
+        Ok(())
+    }
+}
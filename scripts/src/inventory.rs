@@ -0,0 +1,315 @@
+use std::collections::BTreeMap;
+
+use gdnative::prelude::*;
+
+use crate::items::*;
+use crate::modifiers::*;
+use crate::stats::Stats;
+use crate::utils::{get_instance, read_text_file};
+
+// Inventory "class".
+// Autoloaded singleton with the player's coins, carried items and equipment.
+// Armor and trinket bonuses are kept on `PlayerStats` as modifiers, weapons are
+// sent to `Player` through `weapon_changed` and applied on every swing.
+#[derive(NativeClass)]
+#[inherit(Node)]
+#[register_with(Self::register_signals)]
+pub struct Inventory {
+    #[property]
+    items_file: String,
+    // Slots of the bag, each holding one stack
+    #[property(default = 12)]
+    capacity: i64,
+
+    coins: i64,
+    bag: ItemBag,
+}
+
+#[gdnative::methods]
+impl Inventory {
+    fn new(_owner: &Node) -> Self {
+        Inventory {
+            items_file: "res://Data/items.json".to_string(),
+            capacity: DEFAULT_CAPACITY as i64,
+
+            coins: 0,
+            bag: ItemBag::default(),
+        }
+    }
+
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder.add_signal(Signal {
+            name: "coins_changed",
+            args: &[SignalArgument {
+                name: "coins",
+                default: Variant::from_i64(0),
+                export_info: ExportInfo::new(VariantType::I64),
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
+
+        builder.add_signal(Signal {
+            name: "item_added",
+            args: &[
+                SignalArgument {
+                    name: "id",
+                    default: Variant::from_str(""),
+                    export_info: ExportInfo::new(VariantType::GodotString),
+                    usage: PropertyUsage::DEFAULT,
+                },
+                SignalArgument {
+                    name: "count",
+                    default: Variant::from_i64(1),
+                    export_info: ExportInfo::new(VariantType::I64),
+                    usage: PropertyUsage::DEFAULT,
+                },
+            ],
+        });
+
+        // `id` is empty when the slot was emptied
+        builder.add_signal(Signal {
+            name: "equipment_changed",
+            args: &[
+                SignalArgument {
+                    name: "slot",
+                    default: Variant::from_str(""),
+                    export_info: ExportInfo::new(VariantType::GodotString),
+                    usage: PropertyUsage::DEFAULT,
+                },
+                SignalArgument {
+                    name: "id",
+                    default: Variant::from_str(""),
+                    export_info: ExportInfo::new(VariantType::GodotString),
+                    usage: PropertyUsage::DEFAULT,
+                },
+            ],
+        });
+
+        // What the new weapon does, so listeners don't have to borrow `Inventory`
+        // while it is still emitting
+        builder.add_signal(Signal {
+            name: "weapon_changed",
+            args: &[
+                SignalArgument {
+                    name: "damage",
+                    default: Variant::from_i64(0),
+                    export_info: ExportInfo::new(VariantType::I64),
+                    usage: PropertyUsage::DEFAULT,
+                },
+                SignalArgument {
+                    name: "knockback",
+                    default: Variant::from_f64(1.0),
+                    export_info: ExportInfo::new(VariantType::F64),
+                    usage: PropertyUsage::DEFAULT,
+                },
+            ],
+        });
+    }
+
+    #[export]
+    fn _ready(&mut self, _owner: &Node) {
+        let text = match read_text_file(&self.items_file) {
+            Some(text) => text,
+            None => {
+                godot_print!("Could not read item file {}. Check name.", self.items_file);
+                return;
+            }
+        };
+
+        match parse_item_defs(&text) {
+            Ok(defs) => self.bag = ItemBag::new(defs, self.capacity.max(0) as usize),
+            Err(error) => godot_print!("Invalid item file {}: {}", self.items_file, error),
+        }
+    }
+
+    #[export]
+    fn add_coins(&mut self, owner: &Node, amount: i64) {
+        self.coins = (self.coins + amount).max(0);
+        owner.emit_signal("coins_changed", &[self.coins.to_variant()]);
+    }
+
+    #[export]
+    fn get_coins(&self, _owner: &Node) -> i64 {
+        self.coins
+    }
+
+    // Returns how many fit in the bag
+    #[export]
+    fn add_item(&mut self, owner: &Node, id: String, count: i64) -> i64 {
+        if count <= 0 {
+            return 0;
+        }
+
+        match self.bag.add(&id, count as u32) {
+            Ok(0) => 0,
+            Ok(added) => {
+                owner.emit_signal("item_added", &[id.to_variant(), added.to_variant()]);
+                added as i64
+            }
+            Err(error) => {
+                godot_print!("Could not add item: {}", error);
+                0
+            }
+        }
+    }
+
+    #[export]
+    fn remove_item(&mut self, _owner: &Node, id: String, count: i64) -> bool {
+        count > 0 && self.bag.remove(&id, count as u32).is_ok()
+    }
+
+    #[export]
+    fn get_item_count(&self, _owner: &Node, id: String) -> i64 {
+        self.bag.count(&id) as i64
+    }
+
+    // Display name from the item file
+    #[export]
+    fn get_item_name(&self, _owner: &Node, id: String) -> String {
+        self.bag
+            .def(&id)
+            .map(|def| def.name.clone())
+            .unwrap_or_default()
+    }
+
+    #[export]
+    fn equip_item(&mut self, owner: &Node, id: String) -> bool {
+        match self.bag.equip(&id) {
+            Ok(_) => {
+                let slot = self.bag.def(&id).and_then(|def| def.slot);
+                if let Some(slot) = slot {
+                    self.equipment_changed(owner, slot);
+                }
+                true
+            }
+            Err(error) => {
+                godot_print!("Could not equip item: {}", error);
+                false
+            }
+        }
+    }
+
+    #[export]
+    fn unequip_item(&mut self, owner: &Node, slot: String) -> bool {
+        let slot = match EquipSlot::from_name(&slot) {
+            Some(slot) => slot,
+            None => {
+                godot_print!("Unknown equipment slot {}", slot);
+                return false;
+            }
+        };
+
+        match self.bag.unequip(slot) {
+            Ok(_) => {
+                self.equipment_changed(owner, slot);
+                true
+            }
+            Err(error) => {
+                godot_print!("Could not unequip item: {}", error);
+                false
+            }
+        }
+    }
+
+    // Id of the item in `slot`, empty if there is none
+    #[export]
+    fn get_equipped(&self, _owner: &Node, slot: String) -> String {
+        EquipSlot::from_name(&slot)
+            .and_then(|slot| self.bag.equipment().get(&slot).cloned())
+            .unwrap_or_default()
+    }
+}
+
+impl Inventory {
+    pub fn coins(&self) -> i64 {
+        self.coins
+    }
+
+    // Item id -> count
+    pub fn items(&self) -> BTreeMap<String, u32> {
+        self.bag.totals()
+    }
+
+    // Slot name -> item id
+    pub fn equipment(&self) -> BTreeMap<String, String> {
+        self.bag
+            .equipment()
+            .iter()
+            .map(|(slot, id)| (slot.name().to_string(), id.clone()))
+            .collect()
+    }
+
+    pub fn weapon_stats(&self) -> WeaponStats {
+        self.bag.weapon_stats()
+    }
+
+    // Replaces the whole inventory, e.g. from a save file
+    pub fn restore(
+        &mut self,
+        owner: &Node,
+        coins: i64,
+        items: BTreeMap<String, u32>,
+        equipment: &BTreeMap<String, String>,
+    ) {
+        let equipment = equipment
+            .iter()
+            .filter_map(|(slot, id)| EquipSlot::from_name(slot).map(|slot| (slot, id.clone())))
+            .collect();
+
+        for id in self.bag.restore(items, equipment) {
+            godot_print!("Could not restore all of item {}", id);
+        }
+        self.coins = coins;
+
+        owner.emit_signal("coins_changed", &[self.coins.to_variant()]);
+
+        for slot in EquipSlot::ALL.iter() {
+            self.equipment_changed(owner, *slot);
+        }
+    }
+
+    // Moves the bonuses of `slot` to `PlayerStats` and tells listeners, e.g. `Player`
+    fn equipment_changed(&self, owner: &Node, slot: EquipSlot) {
+        let source = format!("equipment:{}", slot.name());
+        let def = self.bag.equipped(slot);
+
+        match get_instance::<Stats>(owner, "/root/PlayerStats") {
+            Ok(stats) => unsafe { stats.assume_safe() }
+                .map_mut(|stats, stats_owner| {
+                    stats.remove_modifiers(&stats_owner, &source);
+
+                    for (name, value) in def.iter().flat_map(|def| def.stats.iter()) {
+                        match StatKind::from_name(name) {
+                            Some(stat) => stats.add_modifier(
+                                &stats_owner,
+                                StatModifier {
+                                    stat,
+                                    op: ModifierOp::Flat,
+                                    value: *value,
+                                    source: source.clone(),
+                                    duration: None,
+                                },
+                            ),
+                            None => godot_print!("Unknown stat {} on equipment", name),
+                        }
+                    }
+                })
+                .expect("PlayerStats should not be borrowed"),
+            Err(error) => godot_print!("{}, equipment bonuses are not applied", error),
+        }
+
+        let id = self.bag.equipment().get(&slot).cloned().unwrap_or_default();
+        owner.emit_signal(
+            "equipment_changed",
+            &[slot.name().to_variant(), id.to_variant()],
+        );
+
+        if slot == EquipSlot::Weapon {
+            let weapon = self.bag.weapon_stats();
+            owner.emit_signal(
+                "weapon_changed",
+                &[weapon.damage.to_variant(), weapon.knockback.to_variant()],
+            );
+        }
+    }
+}
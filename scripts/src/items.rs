@@ -0,0 +1,461 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use serde::Deserialize;
+
+// Stack size of items that don't set `max_stack`; equipment defaults to 1
+const DEFAULT_MAX_STACK: u32 = 99;
+// Slots of a bag that doesn't set its own
+pub const DEFAULT_CAPACITY: usize = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EquipSlot {
+    Weapon,
+    Armor,
+    Trinket,
+}
+
+impl EquipSlot {
+    pub const ALL: [EquipSlot; 3] = [EquipSlot::Weapon, EquipSlot::Armor, EquipSlot::Trinket];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EquipSlot::Weapon => "weapon",
+            EquipSlot::Armor => "armor",
+            EquipSlot::Trinket => "trinket",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        EquipSlot::ALL
+            .iter()
+            .copied()
+            .find(|slot| slot.name() == name)
+    }
+}
+
+// One entry of the item file, keyed by item id.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ItemDef {
+    pub name: String,
+    #[serde(default)]
+    pub max_stack: Option<u32>,
+    #[serde(default)]
+    pub slot: Option<EquipSlot>,
+    // Weapons only: added to every swing, and multiplier of its knockback
+    #[serde(default)]
+    pub damage: i64,
+    #[serde(default = "one")]
+    pub knockback: f32,
+    // Flat stat bonuses while equipped, by `StatKind` name, e.g. `{"defense": 1}`
+    #[serde(default)]
+    pub stats: BTreeMap<String, f64>,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+impl ItemDef {
+    pub fn stack_limit(&self) -> u32 {
+        match (self.max_stack, self.slot) {
+            (Some(max_stack), _) => max_stack,
+            (None, Some(_)) => 1,
+            (None, None) => DEFAULT_MAX_STACK,
+        }
+    }
+}
+
+// What the equipped weapon does to the sword.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WeaponStats {
+    pub damage: i64,
+    pub knockback: f32,
+}
+
+// Bare hands: the combo values as they are
+impl Default for WeaponStats {
+    fn default() -> Self {
+        WeaponStats {
+            damage: 0,
+            knockback: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ItemError {
+    Unknown(String),
+    NotOwned(String),
+    NotEquippable(String),
+    // No free slot and no room left in the stacks of this item
+    Full(String),
+    EmptySlot(EquipSlot),
+}
+
+impl fmt::Display for ItemError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ItemError::Unknown(id) => write!(f, "unknown item {}", id),
+            ItemError::NotOwned(id) => write!(f, "not enough {} in the inventory", id),
+            ItemError::NotEquippable(id) => write!(f, "{} can't be equipped", id),
+            ItemError::Full(id) => write!(f, "no room left for {}", id),
+            ItemError::EmptySlot(slot) => write!(f, "nothing equipped as {}", slot.name()),
+        }
+    }
+}
+
+pub fn parse_item_defs(text: &str) -> Result<HashMap<String, ItemDef>, serde_json::Error> {
+    serde_json::from_str(text)
+}
+
+// Carried items and equipment.
+// The bag has `capacity` slots, each holding one stack of a single item id capped at
+// its `stack_limit`. Adding fills the stacks already there before taking new slots.
+// Equipped items are out of the bag, so equipping takes one and unequipping gives it back.
+pub struct ItemBag {
+    defs: HashMap<String, ItemDef>,
+    capacity: usize,
+    stacks: Vec<(String, u32)>,
+    equipped: BTreeMap<EquipSlot, String>,
+}
+
+impl Default for ItemBag {
+    fn default() -> Self {
+        ItemBag::new(HashMap::new(), DEFAULT_CAPACITY)
+    }
+}
+
+impl ItemBag {
+    pub fn new(defs: HashMap<String, ItemDef>, capacity: usize) -> Self {
+        ItemBag {
+            defs,
+            capacity,
+            stacks: Vec::new(),
+            equipped: BTreeMap::new(),
+        }
+    }
+
+    pub fn def(&self, id: &str) -> Option<&ItemDef> {
+        self.defs.get(id)
+    }
+
+    pub fn count(&self, id: &str) -> u32 {
+        self.stacks
+            .iter()
+            .filter(|(stack_id, _)| stack_id == id)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    // Stacks in slot order
+    pub fn stacks(&self) -> &[(String, u32)] {
+        &self.stacks
+    }
+
+    // Item id -> count over all its stacks
+    pub fn totals(&self) -> BTreeMap<String, u32> {
+        let mut totals = BTreeMap::new();
+        for (id, count) in self.stacks.iter() {
+            *totals.entry(id.clone()).or_insert(0) += count;
+        }
+
+        totals
+    }
+
+    pub fn equipment(&self) -> &BTreeMap<EquipSlot, String> {
+        &self.equipped
+    }
+
+    pub fn equipped(&self, slot: EquipSlot) -> Option<&ItemDef> {
+        self.equipped.get(&slot).and_then(|id| self.def(id))
+    }
+
+    pub fn weapon_stats(&self) -> WeaponStats {
+        match self.equipped(EquipSlot::Weapon) {
+            Some(def) => WeaponStats {
+                damage: def.damage,
+                knockback: def.knockback,
+            },
+            None => WeaponStats::default(),
+        }
+    }
+
+    // Adds up to `count` items and returns how many fit in the bag.
+    pub fn add(&mut self, id: &str, count: u32) -> Result<u32, ItemError> {
+        let limit = self
+            .def(id)
+            .ok_or_else(|| ItemError::Unknown(id.to_string()))?
+            .stack_limit();
+
+        let mut left = count;
+
+        for (stack_id, stack) in self.stacks.iter_mut() {
+            if stack_id == id {
+                let added = left.min(limit.saturating_sub(*stack));
+                *stack += added;
+                left -= added;
+            }
+        }
+
+        while left > 0 && limit > 0 && self.stacks.len() < self.capacity {
+            let added = left.min(limit);
+            self.stacks.push((id.to_string(), added));
+            left -= added;
+        }
+
+        Ok(count - left)
+    }
+
+    // Takes from the last stacks first, so the first ones stay full.
+    pub fn remove(&mut self, id: &str, count: u32) -> Result<(), ItemError> {
+        if self.count(id) < count {
+            return Err(ItemError::NotOwned(id.to_string()));
+        }
+
+        let mut left = count;
+
+        for (stack_id, stack) in self.stacks.iter_mut().rev() {
+            if stack_id == id {
+                let removed = left.min(*stack);
+                *stack -= removed;
+                left -= removed;
+            }
+        }
+
+        self.stacks.retain(|(_, stack)| *stack > 0);
+
+        Ok(())
+    }
+
+    // Equips one `id` from the bag and returns the item it replaced, now back in the bag.
+    pub fn equip(&mut self, id: &str) -> Result<Option<String>, ItemError> {
+        let slot = self
+            .def(id)
+            .ok_or_else(|| ItemError::Unknown(id.to_string()))?
+            .slot
+            .ok_or_else(|| ItemError::NotEquippable(id.to_string()))?;
+
+        self.remove(id, 1)?;
+
+        let previous = self.equipped.insert(slot, id.to_string());
+        if let Some(previous) = &previous {
+            if self.add(previous, 1) != Ok(1) {
+                // Undo, the replaced item would be lost. `id` fits again, its stack
+                // just lost one
+                self.equipped.insert(slot, previous.clone());
+                self.add(id, 1)?;
+
+                return Err(ItemError::Full(previous.clone()));
+            }
+        }
+
+        Ok(previous)
+    }
+
+    // Puts the item in `slot` back in the bag and returns its id.
+    pub fn unequip(&mut self, slot: EquipSlot) -> Result<String, ItemError> {
+        let id = self
+            .equipped
+            .get(&slot)
+            .cloned()
+            .ok_or(ItemError::EmptySlot(slot))?;
+
+        if self.add(&id, 1)? != 1 {
+            return Err(ItemError::Full(id));
+        }
+
+        self.equipped.remove(&slot);
+
+        Ok(id)
+    }
+
+    // Replaces the carried items and equipment, e.g. from a save file. Returns
+    // the ids of items that did not fit or are no longer in the item file.
+    pub fn restore(
+        &mut self,
+        items: BTreeMap<String, u32>,
+        equipped: BTreeMap<EquipSlot, String>,
+    ) -> Vec<String> {
+        self.stacks.clear();
+        self.equipped = equipped;
+
+        items
+            .into_iter()
+            .filter(|(id, count)| self.add(id, *count) != Ok(*count))
+            .map(|(id, _)| id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defs() -> HashMap<String, ItemDef> {
+        parse_item_defs(
+            r#"{
+                "bat_wing": { "name": "Bat Wing", "max_stack": 5 },
+                "iron_sword": { "name": "Iron Sword", "slot": "weapon", "damage": 1 },
+                "heavy_club": { "name": "Heavy Club", "slot": "weapon", "damage": 2, "knockback": 1.5 },
+                "leather_armor": { "name": "Leather Armor", "slot": "armor" },
+                "dagger": { "name": "Dagger", "slot": "weapon", "max_stack": 3 }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn stacks(bag: &ItemBag) -> Vec<(&str, u32)> {
+        bag.stacks()
+            .iter()
+            .map(|(id, count)| (id.as_str(), *count))
+            .collect()
+    }
+
+    #[test]
+    fn stacks_merge_up_to_the_max() {
+        let mut bag = ItemBag::new(defs(), 4);
+
+        assert_eq!(bag.add("bat_wing", 2), Ok(2));
+        assert_eq!(bag.add("bat_wing", 3), Ok(3));
+        assert_eq!(stacks(&bag), vec![("bat_wing", 5)]);
+    }
+
+    #[test]
+    fn overflow_goes_to_a_new_stack() {
+        let mut bag = ItemBag::new(defs(), 4);
+
+        assert_eq!(bag.add("bat_wing", 4), Ok(4));
+        assert_eq!(bag.add("bat_wing", 3), Ok(3));
+        assert_eq!(stacks(&bag), vec![("bat_wing", 5), ("bat_wing", 2)]);
+        assert_eq!(bag.count("bat_wing"), 7);
+        assert_eq!(bag.totals().get("bat_wing"), Some(&7));
+    }
+
+    #[test]
+    fn adding_stops_when_the_bag_is_full() {
+        let mut bag = ItemBag::new(defs(), 2);
+
+        assert_eq!(bag.add("bat_wing", 12), Ok(10));
+        assert_eq!(bag.add("iron_sword", 1), Ok(0));
+        assert_eq!(
+            bag.add("magic_wand", 1),
+            Err(ItemError::Unknown("magic_wand".to_string()))
+        );
+    }
+
+    #[test]
+    fn remove_takes_from_the_last_stack() {
+        let mut bag = ItemBag::new(defs(), 4);
+        bag.add("bat_wing", 7).unwrap();
+
+        assert_eq!(bag.remove("bat_wing", 3), Ok(()));
+        assert_eq!(stacks(&bag), vec![("bat_wing", 4)]);
+        assert_eq!(
+            bag.remove("bat_wing", 5),
+            Err(ItemError::NotOwned("bat_wing".to_string()))
+        );
+    }
+
+    #[test]
+    fn equip_goes_to_the_item_slot() {
+        let mut bag = ItemBag::new(defs(), 4);
+        bag.add("iron_sword", 1).unwrap();
+        bag.add("leather_armor", 1).unwrap();
+
+        assert_eq!(bag.equip("iron_sword"), Ok(None));
+        assert_eq!(bag.equip("leather_armor"), Ok(None));
+        assert_eq!(
+            bag.equipment().get(&EquipSlot::Weapon),
+            Some(&"iron_sword".to_string())
+        );
+        assert_eq!(
+            bag.equipment().get(&EquipSlot::Armor),
+            Some(&"leather_armor".to_string())
+        );
+        assert!(bag.stacks().is_empty());
+        assert_eq!(bag.weapon_stats().damage, 1);
+
+        bag.add("bat_wing", 1).unwrap();
+        assert_eq!(
+            bag.equip("bat_wing"),
+            Err(ItemError::NotEquippable("bat_wing".to_string()))
+        );
+    }
+
+    #[test]
+    fn equip_swaps_the_previous_item_into_the_bag() {
+        let mut bag = ItemBag::new(defs(), 4);
+        bag.add("iron_sword", 1).unwrap();
+        bag.add("heavy_club", 1).unwrap();
+        bag.equip("iron_sword").unwrap();
+
+        assert_eq!(bag.equip("heavy_club"), Ok(Some("iron_sword".to_string())));
+        assert_eq!(stacks(&bag), vec![("iron_sword", 1)]);
+        assert_eq!(
+            bag.weapon_stats(),
+            WeaponStats {
+                damage: 2,
+                knockback: 1.5
+            }
+        );
+    }
+
+    #[test]
+    fn unequip_puts_the_item_back() {
+        let mut bag = ItemBag::new(defs(), 4);
+        bag.add("iron_sword", 1).unwrap();
+        bag.equip("iron_sword").unwrap();
+
+        assert_eq!(bag.unequip(EquipSlot::Weapon), Ok("iron_sword".to_string()));
+        assert_eq!(bag.count("iron_sword"), 1);
+        assert_eq!(bag.weapon_stats(), WeaponStats::default());
+        assert_eq!(
+            bag.unequip(EquipSlot::Weapon),
+            Err(ItemError::EmptySlot(EquipSlot::Weapon))
+        );
+    }
+
+    #[test]
+    fn unequip_into_a_full_bag_keeps_the_item_equipped() {
+        let mut bag = ItemBag::new(defs(), 1);
+        bag.add("iron_sword", 1).unwrap();
+        bag.equip("iron_sword").unwrap();
+        bag.add("bat_wing", 1).unwrap();
+
+        assert_eq!(
+            bag.unequip(EquipSlot::Weapon),
+            Err(ItemError::Full("iron_sword".to_string()))
+        );
+        assert_eq!(bag.weapon_stats().damage, 1);
+        assert_eq!(stacks(&bag), vec![("bat_wing", 1)]);
+    }
+
+    #[test]
+    fn swap_into_a_full_bag_is_undone() {
+        let mut bag = ItemBag::new(defs(), 1);
+        bag.add("iron_sword", 1).unwrap();
+        bag.equip("iron_sword").unwrap();
+        bag.add("dagger", 2).unwrap();
+
+        // One dagger stays in the only slot, the sword has nowhere to go
+        assert_eq!(
+            bag.equip("dagger"),
+            Err(ItemError::Full("iron_sword".to_string()))
+        );
+        assert_eq!(stacks(&bag), vec![("dagger", 2)]);
+        assert_eq!(bag.weapon_stats().damage, 1);
+    }
+
+    #[test]
+    fn swap_can_use_the_slot_it_frees() {
+        let mut bag = ItemBag::new(defs(), 1);
+        bag.add("iron_sword", 1).unwrap();
+        bag.equip("iron_sword").unwrap();
+        bag.add("heavy_club", 1).unwrap();
+
+        assert_eq!(bag.equip("heavy_club"), Ok(Some("iron_sword".to_string())));
+        assert_eq!(stacks(&bag), vec![("iron_sword", 1)]);
+    }
+}
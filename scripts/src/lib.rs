@@ -1,37 +1,85 @@
 use gdnative::prelude::*;
 
+mod ai;
+mod audio_manager;
 mod bat;
+mod bat_benchmark;
 mod camera;
+mod checkpoint;
+mod combo;
+mod damage;
+mod detection_zone;
+mod door;
 mod effect;
+mod game_over;
 mod grass;
+mod health;
 mod health_ui;
 mod hitbox;
 mod hurtbox;
+mod inventory;
+mod items;
+mod loot;
+mod loot_dropper;
+mod loot_tables;
+mod modifiers;
+mod pathfinder;
+mod pathfinding;
+mod pause_menu;
+mod perception;
+mod pickup;
 mod player;
 mod player_hurt_sound;
+mod player_motor;
+mod player_state;
+mod progression;
+mod rng;
+mod rng_service;
+mod save_data;
+mod save_game;
+mod scene_manager;
 mod soft_collision;
+mod spawn;
+mod spawner;
+mod state_machine;
 mod stats;
+mod status;
 mod utils;
-mod wander_controller;
+mod vitals;
+mod weighted_table;
 // mod sword_hitbox;
-// mod player_detection_zone;
 
 // Function that registers all exposed classes to Godot
 fn init(handle: InitHandle) {
+    handle.add_class::<audio_manager::AudioManager>();
     handle.add_class::<bat::Bat>();
+    handle.add_class::<bat_benchmark::BatBenchmark>();
     handle.add_class::<camera::Camera>();
+    handle.add_class::<checkpoint::Checkpoint>();
+    handle.add_class::<detection_zone::DetectionZone>();
+    handle.add_class::<door::Door>();
     handle.add_class::<effect::Effect>();
+    handle.add_class::<game_over::GameOver>();
     handle.add_class::<grass::Grass>();
+    handle.add_class::<health::Health>();
     handle.add_class::<health_ui::HealthUI>();
     handle.add_class::<hitbox::Hitbox>();
     handle.add_class::<hurtbox::Hurtbox>();
+    handle.add_class::<inventory::Inventory>();
+    handle.add_class::<loot_dropper::LootDropper>();
+    handle.add_class::<loot_tables::LootTables>();
+    handle.add_class::<pathfinder::Pathfinder>();
+    handle.add_class::<pause_menu::PauseMenu>();
+    handle.add_class::<pickup::Pickup>();
     handle.add_class::<player::Player>();
     handle.add_class::<player_hurt_sound::PlayerHurtSound>();
+    handle.add_class::<rng_service::RngService>();
+    handle.add_class::<save_game::SaveGame>();
+    handle.add_class::<scene_manager::SceneManager>();
     handle.add_class::<soft_collision::SoftCollision>();
+    handle.add_class::<spawner::Spawner>();
     handle.add_class::<stats::Stats>();
-    handle.add_class::<wander_controller::WanderController>();
     // handle.add_class::<sword_hitbox::SwordHitbox>();
-    // handle.add_class::<player_detection_zone::PlayerDetectionZone>();
 }
 
 // Macro that create the entry-points of the dynamic library.
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::weighted_table::WeightedTable;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+}
+
+impl Rarity {
+    // Weight of an entry that does not set its own
+    pub fn default_weight(self) -> u32 {
+        match self {
+            Rarity::Common => 100,
+            Rarity::Uncommon => 30,
+            Rarity::Rare => 8,
+            Rarity::Epic => 2,
+        }
+    }
+
+    // Matches the `rarity` property exported by `Pickup`
+    pub fn to_i64(self) -> i64 {
+        match self {
+            Rarity::Common => 0,
+            Rarity::Uncommon => 1,
+            Rarity::Rare => 2,
+            Rarity::Epic => 3,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LootKind {
+    // Restores `amount` health
+    Heart,
+    // Adds `amount` to the currency counter
+    Coin,
+    Item { id: String },
+}
+
+impl LootKind {
+    // Matches the `kind` property exported by `Pickup`
+    pub fn to_i64(&self) -> i64 {
+        match self {
+            LootKind::Heart => 0,
+            LootKind::Coin => 1,
+            LootKind::Item { .. } => 2,
+        }
+    }
+}
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct LootEntry {
+    #[serde(flatten)]
+    pub kind: LootKind,
+    #[serde(default)]
+    pub rarity: Rarity,
+    // Defaults to the rarity's weight
+    pub weight: Option<u32>,
+    // Amount range, both inclusive
+    #[serde(default = "one")]
+    pub min: u32,
+    #[serde(default = "one")]
+    pub max: u32,
+}
+
+// One dropped stack.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LootDrop {
+    pub kind: LootKind,
+    pub rarity: Rarity,
+    pub amount: u32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LootTableData {
+    // Always dropped, on top of the rolls
+    pub guaranteed: Vec<LootEntry>,
+    pub rolls: u32,
+    // Weight of rolling nothing
+    pub nothing_weight: u32,
+    pub entries: Vec<LootEntry>,
+}
+
+pub struct LootTable {
+    guaranteed: Vec<LootEntry>,
+    rolls: u32,
+    table: WeightedTable<Option<LootEntry>>,
+}
+
+impl LootTable {
+    pub fn new(data: LootTableData) -> Self {
+        let mut table = WeightedTable::default();
+        table.add(None, data.nothing_weight);
+
+        for entry in data.entries {
+            let weight = entry
+                .weight
+                .unwrap_or_else(|| entry.rarity.default_weight());
+            table.add(Some(entry), weight);
+        }
+
+        LootTable {
+            guaranteed: data.guaranteed,
+            rolls: data.rolls,
+            table,
+        }
+    }
+
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<LootDrop> {
+        let mut drops: Vec<LootDrop> = self
+            .guaranteed
+            .iter()
+            .map(|entry| drop_entry(entry, rng))
+            .collect();
+
+        for _ in 0..self.rolls {
+            if let Some(Some(entry)) = self.table.pick(rng) {
+                drops.push(drop_entry(entry, rng));
+            }
+        }
+
+        drops
+    }
+}
+
+fn drop_entry<R: Rng + ?Sized>(entry: &LootEntry, rng: &mut R) -> LootDrop {
+    LootDrop {
+        kind: entry.kind.clone(),
+        rarity: entry.rarity,
+        amount: rng.gen_range(entry.min..=entry.max.max(entry.min)),
+    }
+}
+
+// Parses a loot file: a JSON object of table id -> table
+pub fn parse_loot_tables(text: &str) -> Result<HashMap<String, LootTableData>, serde_json::Error> {
+    serde_json::from_str(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+
+    fn table(json: &str) -> LootTable {
+        LootTable::new(serde_json::from_str(json).unwrap())
+    }
+
+    fn rng() -> Pcg64 {
+        Pcg64::seed_from_u64(5)
+    }
+
+    #[test]
+    fn entries_default_to_one_common() {
+        let data: LootTableData =
+            serde_json::from_str(r#"{"entries": [{"kind": "item", "id": "potion"}]}"#).unwrap();
+        let entry = &data.entries[0];
+
+        assert_eq!(
+            entry.kind,
+            LootKind::Item {
+                id: "potion".to_string()
+            }
+        );
+        assert_eq!(entry.rarity, Rarity::Common);
+        assert_eq!((entry.min, entry.max), (1, 1));
+        assert_eq!(entry.weight, None);
+        assert_eq!(data.rolls, 0);
+    }
+
+    #[test]
+    fn guaranteed_drops_come_without_rolls() {
+        let table = table(r#"{"guaranteed": [{"kind": "coin", "min": 2, "max": 2}]}"#);
+
+        assert_eq!(
+            table.roll(&mut rng()),
+            vec![LootDrop {
+                kind: LootKind::Coin,
+                rarity: Rarity::Common,
+                amount: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn guaranteed_drops_come_before_the_rolls() {
+        let table = table(
+            r#"{
+                "guaranteed": [{"kind": "heart"}],
+                "rolls": 2,
+                "entries": [{"kind": "coin"}]
+            }"#,
+        );
+        let drops = table.roll(&mut rng());
+
+        assert_eq!(drops.len(), 3);
+        assert_eq!(drops[0].kind, LootKind::Heart);
+        assert!(drops[1..].iter().all(|drop| drop.kind == LootKind::Coin));
+    }
+
+    #[test]
+    fn nothing_can_be_the_only_outcome() {
+        let table = table(
+            r#"{"rolls": 5, "nothing_weight": 10, "entries": [{"kind": "coin", "weight": 0}]}"#,
+        );
+
+        assert!(table.roll(&mut rng()).is_empty());
+    }
+
+    #[test]
+    fn nothing_weight_takes_its_share_of_the_rolls() {
+        let table = table(
+            r#"{"rolls": 1000, "nothing_weight": 3, "entries": [{"kind": "coin", "weight": 1}]}"#,
+        );
+        let drops = table.roll(&mut rng()).len();
+
+        // One roll in four drops a coin
+        assert!((200..300).contains(&drops), "{} coins", drops);
+    }
+
+    #[test]
+    fn rarity_weight_is_used_without_an_explicit_one() {
+        let table = table(
+            r#"{
+                "rolls": 2000,
+                "entries": [
+                    {"kind": "coin"},
+                    {"kind": "heart", "rarity": "epic"}
+                ]
+            }"#,
+        );
+        let hearts = table
+            .roll(&mut rng())
+            .iter()
+            .filter(|drop| drop.kind == LootKind::Heart)
+            .count();
+
+        // 2 out of 102
+        assert!((20..60).contains(&hearts), "{} hearts", hearts);
+    }
+
+    #[test]
+    fn amounts_cover_the_whole_range() {
+        let table = table(r#"{"rolls": 200, "entries": [{"kind": "coin", "min": 2, "max": 4}]}"#);
+        let mut amounts: Vec<u32> = table
+            .roll(&mut rng())
+            .iter()
+            .map(|drop| drop.amount)
+            .collect();
+        amounts.sort_unstable();
+        amounts.dedup();
+
+        assert_eq!(amounts, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn max_below_min_drops_min() {
+        let table = table(r#"{"guaranteed": [{"kind": "coin", "min": 3, "max": 1}]}"#);
+
+        assert_eq!(table.roll(&mut rng())[0].amount, 3);
+    }
+
+    #[test]
+    fn loot_file_maps_ids_to_tables() {
+        let tables = parse_loot_tables(r#"{"bat": {"rolls": 1}, "chest": {"rolls": 3}}"#).unwrap();
+
+        assert_eq!(tables["bat"].rolls, 1);
+        assert_eq!(tables["chest"].rolls, 3);
+        assert!(parse_loot_tables("[1, 2]").is_err());
+    }
+}
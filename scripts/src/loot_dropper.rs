@@ -0,0 +1,107 @@
+use gdnative::api::*;
+use gdnative::prelude::*;
+use rand::Rng;
+
+use crate::loot::*;
+use crate::loot_tables::with_loot_table;
+use crate::rng_service::with_rng;
+use crate::utils::load_scene;
+
+// LootDropper "class".
+// Sits in an enemy or destructible and scatters `Pickup`s from one table of the
+// `LootTables` singleton when the entity calls `drop_loot`.
+#[derive(NativeClass)]
+#[inherit(Node)]
+pub struct LootDropper {
+    // Table id in the loot file, e.g. "bat"
+    #[property]
+    table: String,
+    // Max distance of a pickup from the drop position
+    #[property(default = 8.0)]
+    scatter: f32,
+
+    pickup_scene_load: Option<Ref<PackedScene>>,
+}
+
+#[gdnative::methods]
+impl LootDropper {
+    fn new(_owner: &Node) -> Self {
+        LootDropper {
+            table: String::new(),
+            scatter: 8.0,
+
+            pickup_scene_load: None,
+        }
+    }
+
+    #[export]
+    fn _ready(&mut self, owner: &Node) {
+        if with_loot_table(owner, &self.table, |_| ()).is_none() {
+            godot_print!("Unknown loot table {}", self.table);
+        }
+
+        // Loading scene
+        self.pickup_scene_load = load_scene("res://Pickups/Pickup.tscn");
+        if self.pickup_scene_load.is_none() {
+            godot_print!("Could not load pickup scene. Check name.");
+        }
+    }
+
+    #[export]
+    fn drop_loot(&self, owner: &Node, position: Vector2) {
+        let pickup_scene_load = match &self.pickup_scene_load {
+            Some(pickup_scene_load) => pickup_scene_load,
+            None => return,
+        };
+
+        let scatter = self.scatter;
+        let drops = with_loot_table(owner, &self.table, |loot_table| {
+            with_rng(owner, "loot", |rng| {
+                loot_table
+                    .roll(rng)
+                    .into_iter()
+                    .map(|drop| {
+                        let offset = Vector2::new(
+                            rng.gen_range(-scatter..=scatter),
+                            rng.gen_range(-scatter..=scatter),
+                        );
+                        (drop, position + offset)
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .unwrap_or_default();
+
+        // Pickups go next to the entity, which is this node's parent
+        let parent = owner
+            .get_parent()
+            .and_then(|entity| unsafe { entity.assume_safe() }.get_parent())
+            .expect("LootDropper entity should have a parent");
+        let parent = unsafe { parent.assume_safe() };
+
+        for (drop, position) in drops {
+            let pickup_ref = unsafe { pickup_scene_load.assume_safe() }
+                .instance(PackedScene::GEN_EDIT_STATE_DISABLED)
+                .expect("should be able to instance scene");
+            let pickup = unsafe { pickup_ref.assume_safe() };
+
+            pickup.set("kind", drop.kind.to_i64());
+            if let LootKind::Item { id } = &drop.kind {
+                pickup.set("item_id", id.as_str());
+            }
+            pickup.set("amount", drop.amount as i64);
+            pickup.set("rarity", drop.rarity.to_i64());
+
+            if let Some(pickup) = pickup.cast::<Node2D>() {
+                let position = match parent.cast::<Node2D>() {
+                    Some(parent) => parent.to_local(position),
+                    None => position,
+                };
+                pickup.set_position(position);
+            }
+
+            // Drops can happen inside physics callbacks, where areas can't be added
+            unsafe { parent.call_deferred("add_child", &[pickup_ref.to_variant()]) };
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use gdnative::prelude::*;
+
+use crate::loot::*;
+use crate::utils::read_text_file;
+
+const LOOT_TABLES_PATH: &str = "/root/LootTables";
+
+// LootTables "class".
+// Autoloaded singleton parsing the loot file once for the whole game. Every
+// `LootDropper` rolls its table from here, looked up by id.
+#[derive(NativeClass)]
+#[inherit(Node)]
+pub struct LootTables {
+    #[property]
+    loot_file: String,
+
+    tables: HashMap<String, LootTable>,
+}
+
+#[gdnative::methods]
+impl LootTables {
+    fn new(_owner: &Node) -> Self {
+        LootTables {
+            loot_file: "res://Data/loot_tables.json".to_string(),
+
+            tables: HashMap::new(),
+        }
+    }
+
+    #[export]
+    fn _ready(&mut self, _owner: &Node) {
+        let text = match read_text_file(&self.loot_file) {
+            Some(text) => text,
+            None => {
+                godot_print!("Could not read loot file {}. Check name.", self.loot_file);
+                return;
+            }
+        };
+
+        match parse_loot_tables(&text) {
+            Ok(tables) => {
+                self.tables = tables
+                    .into_iter()
+                    .map(|(id, data)| (id, LootTable::new(data)))
+                    .collect();
+            }
+            Err(error) => godot_print!("Invalid loot file {}: {}", self.loot_file, error),
+        }
+    }
+}
+
+// Runs `f` with the loot table `id`, `None` if there is no such table
+pub fn with_loot_table<R>(node: &Node, id: &str, f: impl FnOnce(&LootTable) -> R) -> Option<R> {
+    let loot_tables = node.get_node(LOOT_TABLES_PATH)?;
+    let loot_tables = unsafe { loot_tables.assume_safe() };
+
+    loot_tables
+        .cast_instance::<LootTables>()?
+        .map(|loot_tables, _| loot_tables.tables.get(id).map(f))
+        .ok()
+        .flatten()
+}
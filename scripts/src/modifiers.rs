@@ -0,0 +1,224 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatKind {
+    Attack,
+    Defense,
+    MoveSpeed,
+    MaxHealth,
+    CritChance,
+}
+
+impl StatKind {
+    pub const ALL: [StatKind; 5] = [
+        StatKind::Attack,
+        StatKind::Defense,
+        StatKind::MoveSpeed,
+        StatKind::MaxHealth,
+        StatKind::CritChance,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StatKind::Attack => "attack",
+            StatKind::Defense => "defense",
+            StatKind::MoveSpeed => "move_speed",
+            StatKind::MaxHealth => "max_health",
+            StatKind::CritChance => "crit_chance",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        StatKind::ALL
+            .iter()
+            .copied()
+            .find(|stat| stat.name() == name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ModifierOp {
+    // Added to the base value
+    Flat,
+    // Summed with the other percent-add modifiers, then applied once
+    PercentAdd,
+    // Each one multiplies the result on its own
+    PercentMultiply,
+}
+
+impl ModifierOp {
+    pub fn from_i64(value: i64) -> Self {
+        match value {
+            1 => ModifierOp::PercentAdd,
+            2 => ModifierOp::PercentMultiply,
+            _ => ModifierOp::Flat,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatModifier {
+    pub stat: StatKind,
+    pub op: ModifierOp,
+    // Flat amount, or fraction for the percent ops (0.1 = +10%)
+    pub value: f64,
+    // Who added it, e.g. an item id, so it can be removed again
+    pub source: String,
+    // Seconds until it expires on its own, `None` for permanent
+    pub duration: Option<f32>,
+}
+
+struct ActiveModifier {
+    modifier: StatModifier,
+    remaining: Option<f32>,
+}
+
+// Modifiers on top of base stats.
+// Values are always recomputed from the base as
+// `(base + flat) * (1 + sum of percent-add) * product of (1 + percent-multiply)`,
+// so the result only depends on the modifiers present, never on past changes.
+#[derive(Default)]
+pub struct ModifierStack {
+    modifiers: Vec<ActiveModifier>,
+}
+
+impl ModifierStack {
+    pub fn add(&mut self, modifier: StatModifier) {
+        self.modifiers.push(ActiveModifier {
+            remaining: modifier.duration,
+            modifier,
+        });
+    }
+
+    // Removes every modifier added by `source` and returns the stats that changed.
+    pub fn remove_source(&mut self, source: &str) -> Vec<StatKind> {
+        let mut changed = Vec::new();
+
+        for active in self.modifiers.iter() {
+            if active.modifier.source == source && !changed.contains(&active.modifier.stat) {
+                changed.push(active.modifier.stat);
+            }
+        }
+
+        self.modifiers
+            .retain(|active| active.modifier.source != source);
+
+        changed
+    }
+
+    // Counts down timed modifiers and returns the stats whose modifiers expired.
+    pub fn tick(&mut self, delta: f32) -> Vec<StatKind> {
+        let mut changed = Vec::new();
+
+        for active in self.modifiers.iter_mut() {
+            if let Some(remaining) = active.remaining.as_mut() {
+                *remaining -= delta;
+
+                if *remaining <= 0.0 && !changed.contains(&active.modifier.stat) {
+                    changed.push(active.modifier.stat);
+                }
+            }
+        }
+
+        self.modifiers
+            .retain(|active| !matches!(active.remaining, Some(remaining) if remaining <= 0.0));
+
+        changed
+    }
+
+    pub fn value(&self, stat: StatKind, base: f64) -> f64 {
+        let mut flat = 0.0;
+        let mut percent_add = 0.0;
+        let mut percent_multiply = 1.0;
+
+        for active in self.modifiers.iter() {
+            let modifier = &active.modifier;
+
+            if modifier.stat != stat {
+                continue;
+            }
+
+            match modifier.op {
+                ModifierOp::Flat => flat += modifier.value,
+                ModifierOp::PercentAdd => percent_add += modifier.value,
+                ModifierOp::PercentMultiply => percent_multiply *= 1.0 + modifier.value,
+            }
+        }
+
+        (base + flat) * (1.0 + percent_add) * percent_multiply
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modifier(op: ModifierOp, value: f64, source: &str) -> StatModifier {
+        StatModifier {
+            stat: StatKind::Attack,
+            op,
+            value,
+            source: source.to_string(),
+            duration: None,
+        }
+    }
+
+    fn modifiers() -> Vec<StatModifier> {
+        vec![
+            modifier(ModifierOp::PercentMultiply, 0.5, "ring"),
+            modifier(ModifierOp::Flat, 2.0, "sword"),
+            modifier(ModifierOp::PercentAdd, 0.25, "sword"),
+            modifier(ModifierOp::PercentAdd, 0.25, "ring"),
+            modifier(ModifierOp::PercentMultiply, 1.0, "potion"),
+        ]
+    }
+
+    #[test]
+    fn flat_then_percent_add_then_percent_multiply() {
+        let mut stack = ModifierStack::default();
+        modifiers().into_iter().for_each(|m| stack.add(m));
+
+        // (2 + 2) * (1 + 0.25 + 0.25) * 1.5 * 2
+        assert_eq!(stack.value(StatKind::Attack, 2.0), 18.0);
+        assert_eq!(stack.value(StatKind::Defense, 3.0), 3.0);
+    }
+
+    #[test]
+    fn insertion_order_does_not_matter() {
+        let mut forward = ModifierStack::default();
+        modifiers().into_iter().for_each(|m| forward.add(m));
+
+        let mut backward = ModifierStack::default();
+        modifiers().into_iter().rev().for_each(|m| backward.add(m));
+
+        assert_eq!(
+            forward.value(StatKind::Attack, 2.0),
+            backward.value(StatKind::Attack, 2.0)
+        );
+    }
+
+    #[test]
+    fn remove_source_removes_all_its_modifiers() {
+        let mut stack = ModifierStack::default();
+        modifiers().into_iter().for_each(|m| stack.add(m));
+
+        assert_eq!(stack.remove_source("sword"), vec![StatKind::Attack]);
+        // 2 * (1 + 0.25) * 1.5 * 2
+        assert_eq!(stack.value(StatKind::Attack, 2.0), 7.5);
+
+        assert!(stack.remove_source("sword").is_empty());
+    }
+
+    #[test]
+    fn timed_modifiers_expire() {
+        let mut stack = ModifierStack::default();
+        stack.add(StatModifier {
+            duration: Some(1.0),
+            ..modifier(ModifierOp::Flat, 2.0, "potion")
+        });
+
+        assert!(stack.tick(0.5).is_empty());
+        assert_eq!(stack.value(StatKind::Attack, 1.0), 3.0);
+
+        assert_eq!(stack.tick(0.5), vec![StatKind::Attack]);
+        assert_eq!(stack.value(StatKind::Attack, 1.0), 1.0);
+    }
+}
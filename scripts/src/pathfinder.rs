@@ -0,0 +1,175 @@
+use gdnative::api::*;
+use gdnative::prelude::*;
+
+use crate::pathfinding::{Cell, Grid};
+use crate::utils::get_typed;
+
+// Pathfinder "class".
+// Grid of walkable cells over the used area of `tile_map`. A cell is blocked when
+// a body on `collision_mask`, e.g. a cliff tile, tree or bush, is within
+// `agent_radius` of it. Enemies find it through the "pathfinder" group.
+#[derive(NativeClass)]
+#[inherit(Node2D)]
+pub struct Pathfinder {
+    #[property]
+    tile_map: NodePath,
+    #[property(default = 16.0)]
+    cell_size: f32,
+    #[property(default = 4.0)]
+    agent_radius: f32,
+    #[property(default = 1)]
+    collision_mask: i64,
+
+    grid: Option<Grid>,
+    origin: Vector2,
+}
+
+#[gdnative::methods]
+impl Pathfinder {
+    // The "constructor" of the class.
+    fn new(_owner: &Node2D) -> Self {
+        Pathfinder {
+            tile_map: NodePath::default(),
+            cell_size: 16.0,
+            agent_radius: 4.0,
+            collision_mask: 1,
+
+            grid: None,
+            origin: Vector2::zero(),
+        }
+    }
+
+    #[export]
+    fn _ready(&self, owner: &Node2D) {
+        owner.add_to_group("pathfinder", false);
+    }
+
+    // The physics space can only be queried once the bodies are in it, so the
+    // grid is built on the first physics frame
+    #[export]
+    fn _physics_process(&mut self, owner: &Node2D, _delta: f64) {
+        self.build(owner);
+        owner.set_physics_process(false);
+    }
+
+    // To call when obstacles were added or removed
+    #[export]
+    fn rebuild(&mut self, owner: &Node2D) {
+        self.build(owner);
+    }
+
+    // Smoothed waypoints from `from` to `to`, empty when there is no path
+    #[export]
+    fn find_path(&self, _owner: &Node2D, from: Vector2, to: Vector2) -> Vector2Array {
+        let mut waypoints = Vector2Array::new();
+
+        for waypoint in self.path(from, to).unwrap_or_default() {
+            waypoints.push(waypoint);
+        }
+
+        waypoints
+    }
+}
+
+impl Pathfinder {
+    // Smoothed waypoints in global coordinates, ending at `to` itself
+    pub fn path(&self, from: Vector2, to: Vector2) -> Option<Vec<Vector2>> {
+        let grid = self.grid.as_ref()?;
+        let cells = grid.find_path(self.cell_at(from), self.cell_at(to))?;
+
+        let mut waypoints: Vec<Vector2> = grid
+            .smooth(&cells)
+            .into_iter()
+            .skip(1)
+            .map(|cell| self.cell_center(cell))
+            .collect();
+
+        // The center of the last cell is only close to the goal
+        waypoints.pop();
+        waypoints.push(to);
+
+        Some(waypoints)
+    }
+
+    fn build(&mut self, owner: &Node2D) {
+        let tile_map = match get_typed::<TileMap>(owner, &self.tile_map.to_string()) {
+            Ok(tile_map) => tile_map,
+            Err(error) => {
+                godot_print!("{}", error);
+                return;
+            }
+        };
+
+        let used = tile_map.get_used_rect();
+        let top_left = tile_map.map_to_world(used.min().to_vector(), false);
+        let bottom_right = tile_map.map_to_world(used.max().to_vector(), false);
+        let (top_left, bottom_right) = (
+            tile_map.to_global(top_left),
+            tile_map.to_global(bottom_right),
+        );
+
+        self.origin = top_left;
+        let size = (bottom_right - top_left) / self.cell_size;
+        let mut grid = Grid::new(size.x.ceil() as i32, size.y.ceil() as i32);
+
+        let world = owner
+            .get_world_2d()
+            .expect("Pathfinder should be in a world");
+        let world = unsafe { world.assume_safe() };
+
+        let space_state = world
+            .direct_space_state()
+            .expect("World should have a space state");
+        let space_state = unsafe { space_state.assume_safe() };
+
+        let extents = self.cell_size / 2.0 + self.agent_radius;
+        let shape = RectangleShape2D::new();
+        shape.set_extents(Vector2::new(extents, extents));
+
+        let query = Physics2DShapeQueryParameters::new().into_shared();
+        let query = unsafe { query.assume_safe() };
+        query.set_shape(shape);
+        query.set_collision_layer(self.collision_mask);
+        query.set_collide_with_bodies(true);
+
+        for y in 0..size.y.ceil() as i32 {
+            for x in 0..size.x.ceil() as i32 {
+                let center = self.cell_center((x, y));
+                query.set_transform(Transform2D::translation(center.x, center.y));
+
+                let blocked = !space_state.intersect_shape(query, 1).is_empty();
+                grid.set_blocked((x, y), blocked);
+            }
+        }
+
+        self.grid = Some(grid);
+    }
+
+    fn cell_at(&self, position: Vector2) -> Cell {
+        let cell = (position - self.origin) / self.cell_size;
+        (cell.x.floor() as i32, cell.y.floor() as i32)
+    }
+
+    fn cell_center(&self, cell: Cell) -> Vector2 {
+        self.origin + Vector2::new(cell.0 as f32 + 0.5, cell.1 as f32 + 0.5) * self.cell_size
+    }
+}
+
+// Waypoints from the pathfinder of the scene `node` is in,
+// None without a pathfinder or a path
+pub fn query_path(node: &Node, from: Vector2, to: Vector2) -> Option<Vec<Vector2>> {
+    let tree = unsafe { node.get_tree()?.assume_safe() };
+    let pathfinders = tree.get_nodes_in_group("pathfinder");
+
+    if pathfinders.is_empty() {
+        return None;
+    }
+
+    let pathfinder = pathfinders.get(0).try_to_object::<Node2D>()?;
+    let pathfinder = unsafe { pathfinder.assume_safe() };
+
+    pathfinder
+        .cast_instance::<Pathfinder>()?
+        .map(|pathfinder, _| pathfinder.path(from, to))
+        .ok()?
+}
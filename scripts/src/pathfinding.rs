@@ -0,0 +1,463 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use gdnative::prelude::*;
+
+pub type Cell = (i32, i32);
+
+// Costs of a straight and a diagonal step, ~1 and ~sqrt(2)
+const STRAIGHT: u32 = 10;
+const DIAGONAL: u32 = 14;
+
+// How far a blocked start or goal is moved to find an open cell
+const SNAP_RADIUS: i32 = 2;
+
+const NEIGHBOURS: [Cell; 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+// Walkable cells of a rectangular area, `(0, 0)` being the top left one.
+pub struct Grid {
+    width: i32,
+    height: i32,
+    blocked: Vec<bool>,
+}
+
+impl Grid {
+    pub fn new(width: i32, height: i32) -> Self {
+        let width = width.max(0);
+        let height = height.max(0);
+
+        Grid {
+            width,
+            height,
+            blocked: vec![false; (width * height) as usize],
+        }
+    }
+
+    pub fn set_blocked(&mut self, cell: Cell, blocked: bool) {
+        if let Some(index) = self.index(cell) {
+            self.blocked[index] = blocked;
+        }
+    }
+
+    // Inside the grid and not blocked
+    pub fn is_open(&self, cell: Cell) -> bool {
+        self.index(cell)
+            .map(|index| !self.blocked[index])
+            .unwrap_or(false)
+    }
+
+    // A* over the 8 neighbours, without cutting corners of blocked cells.
+    // A blocked start or goal is moved to the closest open cell first.
+    pub fn find_path(&self, start: Cell, goal: Cell) -> Option<Vec<Cell>> {
+        let start = self.nearest_open(start, SNAP_RADIUS)?;
+        let goal = self.nearest_open(goal, SNAP_RADIUS)?;
+
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<Cell, Cell> = HashMap::new();
+        let mut costs: HashMap<Cell, u32> = HashMap::new();
+
+        costs.insert(start, 0);
+        open.push(Reverse((heuristic(start, goal), start)));
+
+        while let Some(Reverse((_, cell))) = open.pop() {
+            if cell == goal {
+                let mut path = vec![cell];
+                let mut cell = cell;
+
+                while let Some(previous) = came_from.get(&cell) {
+                    path.push(*previous);
+                    cell = *previous;
+                }
+
+                path.reverse();
+                return Some(path);
+            }
+
+            let cost = costs[&cell];
+
+            for (dx, dy) in NEIGHBOURS.iter() {
+                let next = (cell.0 + dx, cell.1 + dy);
+                if !self.can_step(cell, next) {
+                    continue;
+                }
+
+                let step = if *dx != 0 && *dy != 0 {
+                    DIAGONAL
+                } else {
+                    STRAIGHT
+                };
+                let next_cost = cost + step;
+
+                if costs.get(&next).map(|c| next_cost < *c).unwrap_or(true) {
+                    costs.insert(next, next_cost);
+                    came_from.insert(next, cell);
+                    open.push(Reverse((next_cost + heuristic(next, goal), next)));
+                }
+            }
+        }
+
+        None
+    }
+
+    // Closest open cell to `cell`, looking at most `radius` cells away
+    pub fn nearest_open(&self, cell: Cell, radius: i32) -> Option<Cell> {
+        if self.is_open(cell) {
+            return Some(cell);
+        }
+
+        (1..=radius).find_map(|ring| {
+            let mut ring_cells = Vec::new();
+
+            for x in -ring..=ring {
+                for y in -ring..=ring {
+                    if x.abs() == ring || y.abs() == ring {
+                        ring_cells.push((cell.0 + x, cell.1 + y));
+                    }
+                }
+            }
+
+            ring_cells
+                .into_iter()
+                .filter(|candidate| self.is_open(*candidate))
+                .min_by_key(|candidate| distance_squared(cell, *candidate))
+        })
+    }
+
+    // Whether the straight line between the centers of `a` and `b` only goes through open cells.
+    // Every cell the line touches counts, so it can't slip between two diagonal blocks.
+    pub fn line_of_sight(&self, a: Cell, b: Cell) -> bool {
+        let (dx, dy) = ((b.0 - a.0).abs(), (b.1 - a.1).abs());
+        let (sx, sy) = ((b.0 - a.0).signum(), (b.1 - a.1).signum());
+        let (mut ix, mut iy) = (0, 0);
+        let mut cell = a;
+
+        if !self.is_open(cell) {
+            return false;
+        }
+
+        while ix < dx || iy < dy {
+            let horizontal = (1 + 2 * ix) * dy;
+            let vertical = (1 + 2 * iy) * dx;
+
+            if horizontal == vertical {
+                // Exactly through a corner, both sides of it must be open
+                if !self.is_open((cell.0 + sx, cell.1)) || !self.is_open((cell.0, cell.1 + sy)) {
+                    return false;
+                }
+                cell = (cell.0 + sx, cell.1 + sy);
+                ix += 1;
+                iy += 1;
+            } else if horizontal < vertical {
+                cell.0 += sx;
+                ix += 1;
+            } else {
+                cell.1 += sy;
+                iy += 1;
+            }
+
+            if !self.is_open(cell) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    // Drops the waypoints that can be skipped by going straight to a later one
+    pub fn smooth(&self, path: &[Cell]) -> Vec<Cell> {
+        let mut smoothed = Vec::new();
+        let mut anchor = 0;
+
+        if path.is_empty() {
+            return smoothed;
+        }
+
+        smoothed.push(path[0]);
+
+        while anchor < path.len() - 1 {
+            let next = (anchor + 1..path.len())
+                .rev()
+                .find(|index| self.line_of_sight(path[anchor], path[*index]))
+                .unwrap_or(anchor + 1);
+
+            smoothed.push(path[next]);
+            anchor = next;
+        }
+
+        smoothed
+    }
+
+    fn index(&self, cell: Cell) -> Option<usize> {
+        let (x, y) = cell;
+
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return None;
+        }
+
+        Some((y * self.width + x) as usize)
+    }
+
+    fn can_step(&self, from: Cell, to: Cell) -> bool {
+        if !self.is_open(to) {
+            return false;
+        }
+
+        // Diagonal steps need both sides free
+        from.0 == to.0
+            || from.1 == to.1
+            || (self.is_open((to.0, from.1)) && self.is_open((from.0, to.1)))
+    }
+}
+
+// Octile distance, exact on an open grid
+fn heuristic(a: Cell, b: Cell) -> u32 {
+    let dx = (a.0 - b.0).unsigned_abs();
+    let dy = (a.1 - b.1).unsigned_abs();
+
+    STRAIGHT * dx.max(dy) + (DIAGONAL - STRAIGHT) * dx.min(dy)
+}
+
+fn distance_squared(a: Cell, b: Cell) -> i32 {
+    (a.0 - b.0).pow(2) + (a.1 - b.1).pow(2)
+}
+
+// Waypoints towards a goal that can move, e.g. a chased player.
+// The path is only searched again once the goal moved and `repath_interval` passed.
+pub struct PathFollower {
+    waypoints: Vec<Vector2>,
+    goal: Option<Vector2>,
+    since_repath: f32,
+    repath_interval: f32,
+    arrive_distance: f32,
+}
+
+impl PathFollower {
+    pub fn new(repath_interval: f32, arrive_distance: f32) -> Self {
+        PathFollower {
+            waypoints: Vec::new(),
+            goal: None,
+            since_repath: 0.0,
+            repath_interval,
+            arrive_distance,
+        }
+    }
+
+    // Point to move to now on the way to `goal`. `find_path` returns the waypoints
+    // from a position to a goal; without a path it is straight to the goal.
+    pub fn next(
+        &mut self,
+        position: Vector2,
+        goal: Vector2,
+        delta: f32,
+        find_path: impl FnOnce(Vector2, Vector2) -> Option<Vec<Vector2>>,
+    ) -> Vector2 {
+        self.since_repath += delta;
+
+        let moved = match self.goal {
+            Some(previous) => previous.distance_to(goal) > self.arrive_distance,
+            None => true,
+        };
+
+        if moved && (self.goal.is_none() || self.since_repath >= self.repath_interval) {
+            self.waypoints = find_path(position, goal).unwrap_or_default();
+            self.goal = Some(goal);
+            self.since_repath = 0.0;
+        }
+
+        while let Some(waypoint) = self.waypoints.first() {
+            if position.distance_to(*waypoint) > self.arrive_distance {
+                break;
+            }
+            self.waypoints.remove(0);
+        }
+
+        self.waypoints.first().copied().unwrap_or(goal)
+    }
+
+    // Forgets the path, the next goal gets a new one right away
+    pub fn clear(&mut self) {
+        self.waypoints.clear();
+        self.goal = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `#` is blocked, anything else open
+    fn parse(rows: &[&str]) -> Grid {
+        let mut grid = Grid::new(rows[0].len() as i32, rows.len() as i32);
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                grid.set_blocked((x as i32, y as i32), c == '#');
+            }
+        }
+
+        grid
+    }
+
+    fn cost(path: &[Cell]) -> u32 {
+        path.windows(2)
+            .map(|step| heuristic(step[0], step[1]))
+            .sum()
+    }
+
+    fn assert_walkable(grid: &Grid, path: &[Cell]) {
+        for step in path.windows(2) {
+            assert!(grid.can_step(step[0], step[1]), "{:?} in {:?}", step, path);
+        }
+    }
+
+    #[test]
+    fn open_grid_path_has_octile_cost() {
+        let grid = Grid::new(8, 8);
+        let path = grid.find_path((0, 0), (5, 2)).unwrap();
+
+        assert_eq!(path.first(), Some(&(0, 0)));
+        assert_eq!(path.last(), Some(&(5, 2)));
+        assert_eq!(path.len(), 6);
+        assert_eq!(cost(&path), 3 * STRAIGHT + 2 * DIAGONAL);
+        assert_walkable(&grid, &path);
+    }
+
+    #[test]
+    fn wall_forces_a_detour() {
+        let grid = parse(&[
+            ".....", //
+            "..#..", //
+            "..#..", //
+            "..#..", //
+            ".....", //
+        ]);
+        let path = grid.find_path((0, 2), (4, 2)).unwrap();
+
+        assert!(!path.contains(&(2, 1)) && !path.contains(&(2, 2)) && !path.contains(&(2, 3)));
+        assert!(path.contains(&(2, 0)) || path.contains(&(2, 4)));
+        assert_eq!(cost(&path), 4 * STRAIGHT + 2 * DIAGONAL);
+        assert_walkable(&grid, &path);
+    }
+
+    #[test]
+    fn does_not_cut_corners_between_diagonal_blocks() {
+        let grid = parse(&[
+            "...", //
+            ".#.", //
+            "#..", //
+        ]);
+        let path = grid.find_path((0, 1), (1, 2)).unwrap();
+
+        // The direct diagonal squeezes between two blocks
+        assert_ne!(path, vec![(0, 1), (1, 2)]);
+        assert_walkable(&grid, &path);
+
+        // Two blocks touching at a corner close the only way through
+        let grid = parse(&[
+            ".#", //
+            "#.", //
+        ]);
+        assert_eq!(grid.find_path((0, 0), (1, 1)), None);
+    }
+
+    #[test]
+    fn blocked_start_and_goal_snap_to_open_cells() {
+        let grid = parse(&[
+            "##...", //
+            "##...", //
+            ".....", //
+            "....#", //
+        ]);
+        let path = grid.find_path((0, 0), (4, 3)).unwrap();
+
+        assert!(grid.is_open(path[0]));
+        assert!(grid.is_open(*path.last().unwrap()));
+        assert!(distance_squared((0, 0), path[0]) <= 4);
+        assert!(distance_squared((4, 3), *path.last().unwrap()) <= 1);
+        assert_walkable(&grid, &path);
+    }
+
+    #[test]
+    fn unreachable_goal_has_no_path() {
+        let grid = parse(&[
+            "..#..", //
+            "..#..", //
+            "..#..", //
+        ]);
+        assert_eq!(grid.find_path((0, 1), (4, 1)), None);
+
+        // Nothing open close enough to snap to
+        let grid = parse(&[
+            ".......", //
+            "..###..", //
+            "..###..", //
+            "..###..", //
+        ]);
+        assert!(grid.nearest_open((3, 2), 2).is_some());
+        assert_eq!(grid.nearest_open((3, 2), 0), None);
+    }
+
+    #[test]
+    fn smooth_drops_collinear_waypoints_and_keeps_corners() {
+        let grid = parse(&[
+            ".....", //
+            "####.", //
+            ".....", //
+        ]);
+        let path = grid.find_path((0, 0), (0, 2)).unwrap();
+        let smoothed = grid.smooth(&path);
+
+        assert_eq!(smoothed.first(), Some(&(0, 0)));
+        assert_eq!(smoothed.last(), Some(&(0, 2)));
+        assert!(smoothed.len() < path.len());
+        // Around the end of the wall, not through it
+        assert!(smoothed.contains(&(4, 0)) || smoothed.contains(&(3, 0)));
+        for leg in smoothed.windows(2) {
+            assert!(grid.line_of_sight(leg[0], leg[1]), "{:?}", leg);
+        }
+
+        let straight = vec![(0, 0), (1, 0), (2, 0), (3, 0)];
+        assert_eq!(Grid::new(4, 1).smooth(&straight), vec![(0, 0), (3, 0)]);
+    }
+
+    #[test]
+    fn follower_repaths_only_when_goal_moved_and_interval_passed() {
+        let searches = std::cell::Cell::new(0);
+        let find_path = |_: Vector2, goal: Vector2| {
+            searches.set(searches.get() + 1);
+            Some(vec![Vector2::new(10.0, 0.0), goal])
+        };
+
+        let mut follower = PathFollower::new(0.5, 4.0);
+        let position = Vector2::zero();
+        let goal = Vector2::new(50.0, 0.0);
+
+        let waypoint = follower.next(position, goal, 0.1, find_path);
+        assert_eq!(waypoint, Vector2::new(10.0, 0.0));
+        assert_eq!(searches.get(), 1);
+
+        // Same goal, or moved less than `arrive_distance`, even long after
+        follower.next(position, goal, 1.0, find_path);
+        follower.next(position, Vector2::new(52.0, 0.0), 1.0, find_path);
+        assert_eq!(searches.get(), 1);
+
+        // Moved, and the interval passed long ago
+        follower.next(position, Vector2::new(80.0, 0.0), 0.1, find_path);
+        assert_eq!(searches.get(), 2);
+
+        // Moved again, but too soon
+        follower.next(position, Vector2::new(100.0, 0.0), 0.2, find_path);
+        assert_eq!(searches.get(), 2);
+        follower.next(position, Vector2::new(100.0, 0.0), 0.4, find_path);
+        assert_eq!(searches.get(), 3);
+    }
+}
@@ -0,0 +1,295 @@
+use gdnative::api::*;
+use gdnative::prelude::*;
+
+use crate::audio_manager;
+use crate::save_game;
+use crate::utils::{disable, get_ref, play_sound, NodeError};
+
+#[derive(Clone, Copy)]
+enum MenuOption {
+    Resume,
+    Settings,
+    Quit,
+}
+
+// In the same order as the labels under `Options`
+const OPTIONS: [MenuOption; 3] = [MenuOption::Resume, MenuOption::Settings, MenuOption::Quit];
+
+#[derive(Clone, Copy)]
+enum SettingsOption {
+    // `AudioManager` bus and the text shown before its volume
+    Volume(&'static str, &'static str),
+    Back,
+}
+
+// In the same order as the labels under `Settings`
+const SETTINGS: [SettingsOption; 4] = [
+    SettingsOption::Volume("master", "Master"),
+    SettingsOption::Volume("music", "Music"),
+    SettingsOption::Volume("sfx", "Sound"),
+    SettingsOption::Back,
+];
+
+// Change of a bus volume per left/right press
+const VOLUME_STEP: f64 = 0.1;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Page {
+    Options,
+    Settings,
+}
+
+// PauseMenu "class".
+// Pauses the scene tree on the `pause` action. The scene sets its pause mode to
+// process, so it keeps reading input while `Player`, `Bat` and the rest are stopped.
+#[derive(NativeClass)]
+#[inherit(Control)]
+pub struct PauseMenu {
+    page: Page,
+    selected: usize,
+    // Waiting for the select sound before closing the game
+    quitting: bool,
+    options: Ref<Control>,
+    option_labels: Vec<Ref<Label>>,
+    settings: Ref<Control>,
+    settings_labels: Vec<Ref<Label>>,
+    pause_sound: Ref<AudioStreamPlayer>,
+    unpause_sound: Ref<AudioStreamPlayer>,
+    move_sound: Ref<AudioStreamPlayer>,
+    select_sound: Ref<AudioStreamPlayer>,
+}
+
+#[gdnative::methods]
+impl PauseMenu {
+    // The "constructor" of the class.
+    fn new(_owner: &Control) -> Self {
+        PauseMenu {
+            page: Page::Options,
+            selected: 0,
+            quitting: false,
+            options: Control::new().into_shared(),
+            option_labels: Vec::new(),
+            settings: Control::new().into_shared(),
+            settings_labels: Vec::new(),
+            pause_sound: AudioStreamPlayer::new().into_shared(),
+            unpause_sound: AudioStreamPlayer::new().into_shared(),
+            move_sound: AudioStreamPlayer::new().into_shared(),
+            select_sound: AudioStreamPlayer::new().into_shared(),
+        }
+    }
+
+    #[export]
+    fn _ready(&mut self, owner: TRef<Control>) {
+        owner.set_visible(false);
+
+        // The game goes on without a pause menu
+        if let Err(error) = self.find_nodes(&owner) {
+            disable(&owner, error);
+            return;
+        }
+
+        let select_sound = unsafe { self.select_sound.assume_safe() };
+        select_sound
+            .connect(
+                "finished",
+                owner,
+                "_on_select_sound_finished",
+                VariantArray::new_shared(),
+                1,
+            )
+            .unwrap();
+    }
+
+    #[export]
+    fn _process(&mut self, owner: &Control, _delta: f64) {
+        if self.quitting {
+            return;
+        }
+
+        let input = Input::godot_singleton();
+
+        if Input::is_action_just_pressed(input, "pause") {
+            if owner.is_visible() {
+                self.resume(owner);
+            } else if !self.is_paused(owner) {
+                // Something else, e.g. a game over screen, may already hold the pause
+                self.pause(owner);
+            }
+            return;
+        }
+
+        if !owner.is_visible() {
+            return;
+        }
+
+        if Input::is_action_just_pressed(input, "ui_up") {
+            self.move_selection(owner, self.option_count() - 1);
+        } else if Input::is_action_just_pressed(input, "ui_down") {
+            self.move_selection(owner, 1);
+        } else if Input::is_action_just_pressed(input, "ui_accept") {
+            self.select(owner);
+        } else if self.page == Page::Settings {
+            if Input::is_action_just_pressed(input, "ui_left") {
+                self.change_volume(owner, -VOLUME_STEP);
+            } else if Input::is_action_just_pressed(input, "ui_right") {
+                self.change_volume(owner, VOLUME_STEP);
+            } else if Input::is_action_just_pressed(input, "ui_cancel") {
+                self.show_page(owner, Page::Options);
+            }
+        }
+    }
+
+    // Accepting signal
+    #[export]
+    fn _on_select_sound_finished(&mut self, owner: &Control) {
+        if self.quitting {
+            quit(owner);
+        }
+    }
+}
+
+impl PauseMenu {
+    fn find_nodes(&mut self, owner: &Control) -> Result<(), NodeError> {
+        self.options = get_ref::<Control>(owner, "Options")?;
+        self.option_labels = ["Options/Resume", "Options/Settings", "Options/Quit"]
+            .iter()
+            .map(|path| get_ref::<Label>(owner, path))
+            .collect::<Result<_, _>>()?;
+
+        self.settings = get_ref::<Control>(owner, "Settings")?;
+        self.settings_labels = [
+            "Settings/Master",
+            "Settings/Music",
+            "Settings/Sound",
+            "Settings/Back",
+        ]
+        .iter()
+        .map(|path| get_ref::<Label>(owner, path))
+        .collect::<Result<_, _>>()?;
+
+        self.pause_sound = get_ref::<AudioStreamPlayer>(owner, "PauseSound")?;
+        self.unpause_sound = get_ref::<AudioStreamPlayer>(owner, "UnpauseSound")?;
+        self.move_sound = get_ref::<AudioStreamPlayer>(owner, "MoveSound")?;
+        self.select_sound = get_ref::<AudioStreamPlayer>(owner, "SelectSound")?;
+
+        Ok(())
+    }
+
+    fn is_paused(&self, owner: &Control) -> bool {
+        unsafe { owner.get_tree().unwrap().assume_safe().is_paused() }
+    }
+
+    fn set_paused(&self, owner: &Control, paused: bool) {
+        unsafe { owner.get_tree().unwrap().assume_safe().set_pause(paused) };
+    }
+
+    fn pause(&mut self, owner: &Control) {
+        self.set_paused(owner, true);
+        owner.set_visible(true);
+
+        self.show_page(owner, Page::Options);
+        play_sound(&self.pause_sound);
+    }
+
+    fn resume(&mut self, owner: &Control) {
+        self.set_paused(owner, false);
+        owner.set_visible(false);
+
+        play_sound(&self.unpause_sound);
+    }
+
+    fn show_page(&mut self, owner: &Control, page: Page) {
+        self.page = page;
+        self.selected = 0;
+
+        set_visible(&self.options, page == Page::Options);
+        set_visible(&self.settings, page == Page::Settings);
+
+        self.update_labels(owner);
+    }
+
+    fn option_count(&self) -> usize {
+        match self.page {
+            Page::Options => OPTIONS.len(),
+            Page::Settings => SETTINGS.len(),
+        }
+    }
+
+    // `step` is added modulo the option count, so `len - 1` moves up
+    fn move_selection(&mut self, owner: &Control, step: usize) {
+        self.selected = (self.selected + step) % self.option_count();
+
+        self.update_labels(owner);
+        play_sound(&self.move_sound);
+    }
+
+    fn select(&mut self, owner: &Control) {
+        if self.page == Page::Settings {
+            if let SettingsOption::Back = SETTINGS[self.selected] {
+                play_sound(&self.select_sound);
+                self.show_page(owner, Page::Options);
+            }
+            return;
+        }
+
+        match OPTIONS[self.selected] {
+            MenuOption::Resume => self.resume(owner),
+            MenuOption::Settings => {
+                play_sound(&self.select_sound);
+                self.show_page(owner, Page::Settings);
+            }
+            MenuOption::Quit => {
+                save_game::save(owner);
+
+                // Closes the game once the sound is over, see `_on_select_sound_finished`,
+                // or right away when there is no sound to wait for
+                self.quitting = true;
+                play_sound(&self.select_sound);
+
+                if !unsafe { self.select_sound.assume_safe() }.is_playing() {
+                    quit(owner);
+                }
+            }
+        }
+    }
+
+    fn change_volume(&mut self, owner: &Control, step: f64) {
+        if let SettingsOption::Volume(bus, _) = SETTINGS[self.selected] {
+            let volume = audio_manager::bus_volume(owner, bus) + step;
+            audio_manager::set_bus_volume(owner, bus, volume);
+
+            self.update_labels(owner);
+            play_sound(&self.move_sound);
+        }
+    }
+
+    // Dims every option but the selected one and shows the bus volumes
+    fn update_labels(&self, owner: &Control) {
+        let labels = match self.page {
+            Page::Options => &self.option_labels,
+            Page::Settings => &self.settings_labels,
+        };
+
+        for (index, label) in labels.iter().enumerate() {
+            let label = unsafe { label.assume_safe() };
+
+            if self.page == Page::Settings {
+                if let SettingsOption::Volume(bus, name) = SETTINGS[index] {
+                    let volume = audio_manager::bus_volume(owner, bus);
+                    label.set_text(format!("{} {}%", name, (volume * 100.0).round()));
+                }
+            }
+
+            let alpha = if index == self.selected { 1.0 } else { 0.5 };
+            label.set_modulate(Color::rgba(1.0, 1.0, 1.0, alpha));
+        }
+    }
+}
+
+fn set_visible(node: &Ref<Control>, visible: bool) {
+    unsafe { node.assume_safe() }.set_visible(visible);
+}
+
+fn quit(owner: &Control) {
+    unsafe { owner.get_tree().unwrap().assume_safe().quit(0) };
+}
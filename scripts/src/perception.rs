@@ -0,0 +1,231 @@
+use gdnative::prelude::*;
+
+// How long things take to notice, forget and give up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MemoryTimes {
+    // Seconds the target must stay in sight before the enemy reacts
+    pub notice_time: f32,
+    // Seconds out of sight before a chase turns into a search
+    pub lose_time: f32,
+    // Seconds of searching before giving up
+    pub search_time: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Awareness {
+    Unaware,
+    Noticing {
+        seen_for: f32,
+    },
+    Engaged {
+        position: Vector2,
+        unseen_for: f32,
+    },
+    Searching {
+        position: Vector2,
+        searched_for: f32,
+    },
+}
+
+// What an enemy remembers of its target between line-of-sight checks.
+// Hysteresis on both ends: a glimpse shorter than `notice_time` is ignored, and
+// breaking line of sight for less than `lose_time` doesn't stop the chase.
+pub struct TargetMemory {
+    times: MemoryTimes,
+    awareness: Awareness,
+}
+
+impl TargetMemory {
+    pub fn new(times: MemoryTimes) -> Self {
+        TargetMemory {
+            times,
+            awareness: Awareness::Unaware,
+        }
+    }
+
+    // `seen` is the target position when it is in sight this frame
+    pub fn update(&mut self, seen: Option<Vector2>, delta: f32) {
+        let times = self.times;
+
+        self.awareness = match (self.awareness, seen) {
+            (Awareness::Unaware, Some(position)) => notice(0.0, position, delta, times),
+            (Awareness::Noticing { seen_for }, Some(position)) => {
+                notice(seen_for, position, delta, times)
+            }
+            (Awareness::Unaware, None) | (Awareness::Noticing { .. }, None) => Awareness::Unaware,
+            // Already alert, no need to notice again
+            (Awareness::Engaged { .. }, Some(position))
+            | (Awareness::Searching { .. }, Some(position)) => Awareness::Engaged {
+                position,
+                unseen_for: 0.0,
+            },
+            (
+                Awareness::Engaged {
+                    position,
+                    unseen_for,
+                },
+                None,
+            ) => {
+                if unseen_for + delta >= times.lose_time {
+                    Awareness::Searching {
+                        position,
+                        searched_for: 0.0,
+                    }
+                } else {
+                    Awareness::Engaged {
+                        position,
+                        unseen_for: unseen_for + delta,
+                    }
+                }
+            }
+            (
+                Awareness::Searching {
+                    position,
+                    searched_for,
+                },
+                None,
+            ) => {
+                if searched_for + delta >= times.search_time {
+                    Awareness::Unaware
+                } else {
+                    Awareness::Searching {
+                        position,
+                        searched_for: searched_for + delta,
+                    }
+                }
+            }
+        };
+    }
+
+    // Where to chase: the target while engaged, or where it was last seen during the grace time
+    pub fn target(&self) -> Option<Vector2> {
+        match self.awareness {
+            Awareness::Engaged { position, .. } => Some(position),
+            _ => None,
+        }
+    }
+
+    // Where to search after losing the target
+    pub fn last_known(&self) -> Option<Vector2> {
+        match self.awareness {
+            Awareness::Searching { position, .. } => Some(position),
+            _ => None,
+        }
+    }
+}
+
+fn notice(seen_for: f32, position: Vector2, delta: f32, times: MemoryTimes) -> Awareness {
+    if seen_for + delta >= times.notice_time {
+        Awareness::Engaged {
+            position,
+            unseen_for: 0.0,
+        }
+    } else {
+        Awareness::Noticing {
+            seen_for: seen_for + delta,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMES: MemoryTimes = MemoryTimes {
+        notice_time: 0.5,
+        lose_time: 1.0,
+        search_time: 2.0,
+    };
+
+    fn seen() -> Option<Vector2> {
+        Some(Vector2::new(10.0, 20.0))
+    }
+
+    // Updates `memory` `frames` times, 0.25 seconds each
+    fn run(memory: &mut TargetMemory, seen: Option<Vector2>, frames: usize) {
+        for _ in 0..frames {
+            memory.update(seen, 0.25);
+        }
+    }
+
+    fn engaged() -> TargetMemory {
+        let mut memory = TargetMemory::new(TIMES);
+        run(&mut memory, seen(), 2);
+        memory
+    }
+
+    #[test]
+    fn target_is_noticed_after_notice_time() {
+        let mut memory = TargetMemory::new(TIMES);
+
+        run(&mut memory, seen(), 1);
+        assert_eq!(memory.target(), None);
+
+        run(&mut memory, seen(), 1);
+        assert_eq!(memory.target(), seen());
+    }
+
+    #[test]
+    fn glimpses_shorter_than_notice_time_are_ignored() {
+        let mut memory = TargetMemory::new(TIMES);
+
+        for _ in 0..4 {
+            run(&mut memory, seen(), 1);
+            run(&mut memory, None, 1);
+        }
+
+        assert_eq!(memory.target(), None);
+        assert_eq!(memory.last_known(), None);
+    }
+
+    #[test]
+    fn short_loss_of_sight_keeps_the_chase() {
+        let mut memory = engaged();
+
+        run(&mut memory, None, 3);
+        assert_eq!(memory.target(), seen());
+
+        // Seen again before `lose_time`, the grace time starts over
+        let moved = Some(Vector2::new(30.0, 20.0));
+        run(&mut memory, moved, 1);
+        run(&mut memory, None, 3);
+        assert_eq!(memory.target(), moved);
+    }
+
+    #[test]
+    fn lost_target_is_searched_at_its_last_known_position() {
+        let mut memory = engaged();
+        run(&mut memory, Some(Vector2::new(40.0, 0.0)), 1);
+
+        run(&mut memory, None, 4);
+        assert_eq!(memory.target(), None);
+        assert_eq!(memory.last_known(), Some(Vector2::new(40.0, 0.0)));
+    }
+
+    #[test]
+    fn target_seen_while_searching_is_chased_at_once() {
+        let mut memory = engaged();
+        run(&mut memory, None, 4);
+
+        run(&mut memory, seen(), 1);
+        assert_eq!(memory.target(), seen());
+        assert_eq!(memory.last_known(), None);
+    }
+
+    #[test]
+    fn search_gives_up_after_search_time() {
+        let mut memory = engaged();
+        run(&mut memory, None, 4);
+
+        run(&mut memory, None, 7);
+        assert_eq!(memory.last_known(), seen());
+
+        run(&mut memory, None, 1);
+        assert_eq!(memory.last_known(), None);
+        assert_eq!(memory.target(), None);
+
+        // Has to notice the target all over again
+        run(&mut memory, seen(), 1);
+        assert_eq!(memory.target(), None);
+    }
+}
@@ -0,0 +1,132 @@
+use gdnative::api::*;
+use gdnative::prelude::*;
+
+use crate::utils::get_typed;
+
+// Pickup "class".
+// Collected when the player walks over it: hearts heal `PlayerStats`,
+// coins and items go to the `Inventory` singleton.
+#[derive(NativeClass)]
+#[inherit(Area2D)]
+pub struct Pickup {
+    // 0 = heart, 1 = coin, 2 = item
+    #[property(default = 1)]
+    kind: i64,
+    #[property]
+    item_id: String,
+    #[property(default = 1)]
+    amount: i64,
+    // 0 = common, 1 = uncommon, 2 = rare, 3 = epic
+    #[property(default = 0)]
+    rarity: i64,
+}
+
+#[gdnative::methods]
+impl Pickup {
+    // The "constructor" of the class.
+    fn new(_owner: &Area2D) -> Self {
+        Pickup {
+            kind: 1,
+            item_id: String::new(),
+            amount: 1,
+            rarity: 0,
+        }
+    }
+
+    #[export]
+    fn _ready(&self, owner: TRef<Area2D>) {
+        owner
+            .connect(
+                "body_entered",
+                owner,
+                "_on_body_entered",
+                VariantArray::new_shared(),
+                1,
+            )
+            .unwrap();
+    }
+
+    #[export]
+    fn _draw(&self, owner: &Area2D) {
+        owner.draw_circle(Vector2::zero(), 4.0, self.color());
+    }
+
+    // Accepting signal
+    #[export]
+    fn _on_body_entered(&mut self, owner: &Area2D, body: Ref<Node>) {
+        let body = unsafe { body.assume_safe() };
+        if !body.is_in_group("player") {
+            return;
+        }
+
+        if self.collect(owner) {
+            owner.queue_free();
+        }
+    }
+}
+
+impl Pickup {
+    fn color(&self) -> Color {
+        match self.kind {
+            0 => Color::rgb(0.9, 0.2, 0.3),
+            1 => Color::rgb(1.0, 0.8, 0.2),
+            _ => match self.rarity {
+                1 => Color::rgb(0.3, 0.9, 0.3),
+                2 => Color::rgb(0.3, 0.5, 1.0),
+                3 => Color::rgb(0.7, 0.3, 0.9),
+                _ => Color::rgb(1.0, 1.0, 1.0),
+            },
+        }
+    }
+
+    // Returns false if the pickup should stay, e.g. a heart at full health
+    // or items that didn't all fit in the inventory
+    fn collect(&mut self, owner: &Area2D) -> bool {
+        // Hearts go to `PlayerStats`, coins and items to `Inventory`
+        let path = match self.kind {
+            0 => "/root/PlayerStats",
+            _ => "/root/Inventory",
+        };
+
+        let target = match get_typed::<Node>(owner, path) {
+            Ok(target) => target,
+            Err(error) => {
+                godot_print!("{}", error);
+                return false;
+            }
+        };
+
+        match self.kind {
+            0 => {
+                let stats = target;
+
+                let health = stats.get("health").to_i64();
+                if health >= stats.get("max_health").to_i64() {
+                    return false;
+                }
+
+                unsafe { stats.call("set_health", &[(health + self.amount).to_variant()]) };
+            }
+            1 => {
+                let inventory = target;
+                unsafe { inventory.call("add_coins", &[self.amount.to_variant()]) };
+            }
+            _ => {
+                let inventory = target;
+                let added = unsafe {
+                    inventory.call(
+                        "add_item",
+                        &[self.item_id.to_variant(), self.amount.to_variant()],
+                    )
+                };
+
+                self.amount -= added.to_i64();
+                if self.amount > 0 {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
@@ -1,10 +1,34 @@
-use crate::utils::*;
+use crate::audio_manager;
+use crate::checkpoint;
+use crate::combo::*;
+use crate::damage::DamageInfo;
+use crate::inventory::Inventory;
+use crate::items::WeaponStats;
+use crate::player_motor::*;
+use crate::player_state::*;
+use crate::save_game;
+use crate::scene_manager;
+use crate::stats::Stats;
+use crate::utils::{disable, get_instance, get_ref, get_typed, NodeError};
 use gdnative::api::*;
 use gdnative::prelude::*;
 
+// Signals of the singletons the player listens to: (node, signal, method).
+// Bound when entering the tree and unbound when leaving it, so a player that is
+// removed or added again never leaves a stale connection behind.
+const SINGLETON_SIGNALS: [(&str, &str, &str); 2] = [
+    ("/root/PlayerStats", "no_health", "_on_stats_no_health"),
+    (
+        "/root/Inventory",
+        "weapon_changed",
+        "_on_inventory_weapon_changed",
+    ),
+];
+
 // Player "class".
 #[derive(NativeClass)]
 #[inherit(KinematicBody2D)]
+#[register_with(Self::register_signals)]
 #[allow(non_snake_case)]
 pub struct Player {
     #[property(path = "base/acceleration", default = 500.0)]
@@ -15,23 +39,38 @@ pub struct Player {
     friction: f32,
     #[property(path = "base/roll_speed", default = 120.0)]
     roll_speed: f32,
-
-    velocity: Vector2,
-    state: PlayerState,
-    input_vector: Vector2,
-    roll_vector: Vector2,
+    #[property(path = "base/stagger_duration", default = 0.2)]
+    stagger_duration: f32,
+    #[property(path = "roll/invincibility_duration", default = 0.35)]
+    roll_invincibility_duration: f64,
+    #[property(path = "roll/stamina_cost", default = 25.0)]
+    roll_stamina_cost: f64,
+    #[property(path = "attack/stamina_cost", default = 10.0)]
+    attack_stamina_cost: f64,
+    #[property(path = "attack/buffer_window", default = 0.25)]
+    attack_buffer_window: f32,
+    #[property(path = "attack/chain_window", default = 0.3)]
+    attack_chain_window: f32,
+    #[property(path = "death/respawn_invincibility", default = 1.0)]
+    respawn_invincibility: f64,
+
+    context: PlayerContext,
+    state_machine: PlayerStateMachine,
+    stagger_time: f32,
+    combo: ComboChain,
+    attack_restart: bool,
+    // Equipped weapon, applied on top of each combo step
+    weapon: WeaponStats,
+    // Where `respawn` puts the player back
+    respawn_position: Vector2,
+    animation_tree: Ref<AnimationTree>,
+    animation_state: Ref<AnimationNodeStateMachinePlayback>,
     sword_hitbox: Ref<Area2D>,
-    stats: Ref<Node>,
-    hurtbox: Ref<Node>,
-    player_hurt_sound_load: Ref<PackedScene>,
-    blink_animation_player: Ref<Node>,
-}
-
-#[allow(clippy::upper_case_acronyms)]
-enum PlayerState {
-    MOVE,
-    ROLL,
-    ATTACK,
+    // `PlayerStats`, found in `_ready`
+    stats: Option<Instance<Stats, Shared>>,
+    hurtbox: Ref<Area2D>,
+    blink_animation_player: Ref<AnimationPlayer>,
+    death_animation_player: Ref<AnimationPlayer>,
 }
 
 // Player implementation.
@@ -44,165 +83,380 @@ impl Player {
             max_speed: 80.0,
             friction: 500.0,
             roll_speed: 120.0,
-
-            velocity: Vector2::zero(),
-            state: PlayerState::MOVE,
-            input_vector: Vector2::zero(),
-            roll_vector: Vector2::new(0.0, 1.0),
+            stagger_duration: 0.2,
+            roll_invincibility_duration: 0.35,
+            roll_stamina_cost: 25.0,
+            attack_stamina_cost: 10.0,
+            attack_buffer_window: 0.25,
+            attack_chain_window: 0.3,
+            respawn_invincibility: 1.0,
+
+            context: PlayerContext {
+                motion: MotorState::default(),
+                can_interact: false,
+            },
+            state_machine: player_state_machine(),
+            stagger_time: 0.0,
+            combo: ComboChain::new(Vec::new(), 0.0, 0.0),
+            attack_restart: false,
+            weapon: WeaponStats::default(),
+            respawn_position: Vector2::zero(),
+            animation_tree: AnimationTree::new().into_shared(),
+            animation_state: AnimationNodeStateMachinePlayback::new().into_shared(),
             sword_hitbox: Area2D::new().into_shared(),
-            stats: Node::new().into_shared(),
-            hurtbox: Node::new().into_shared(),
-            player_hurt_sound_load: PackedScene::new().into_shared(),
-            blink_animation_player: Node::new().into_shared(),
+            stats: None,
+            hurtbox: Area2D::new().into_shared(),
+            blink_animation_player: AnimationPlayer::new().into_shared(),
+            death_animation_player: AnimationPlayer::new().into_shared(),
+        }
+    }
+
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder.add_signal(Signal {
+            name: "died",
+            args: &[],
+        });
+
+        builder.add_signal(Signal {
+            name: "respawned",
+            args: &[],
+        });
+    }
+
+    #[export]
+    fn _enter_tree(&self, owner: TRef<KinematicBody2D>) {
+        for (path, signal, method) in SINGLETON_SIGNALS.iter() {
+            let node = match get_typed::<Node>(&owner, path) {
+                Ok(node) => node,
+                Err(error) => {
+                    godot_print!("{}, {} is not connected", error, signal);
+                    continue;
+                }
+            };
+
+            if !node.is_connected(*signal, owner, *method) {
+                node.connect(*signal, owner, *method, VariantArray::new_shared(), 1)
+                    .unwrap();
+            }
+        }
+    }
+
+    #[export]
+    fn _exit_tree(&self, owner: TRef<KinematicBody2D>) {
+        for (path, signal, method) in SINGLETON_SIGNALS.iter() {
+            if let Some(node) = owner.get_node(*path) {
+                let node = unsafe { node.assume_safe() };
+
+                if node.is_connected(*signal, owner, *method) {
+                    node.disconnect(*signal, owner, *method);
+                }
+            }
         }
     }
 
     #[export]
     fn _ready(&mut self, owner: TRef<KinematicBody2D>) {
-        let animation_tree = owner.get_node("AnimationTree").expect("Node Should Exist");
-        let animation_tree = unsafe { animation_tree.assume_safe() };
-        let animation_tree = animation_tree
-            .cast::<AnimationTree>()
-            .expect("Node should cast to AnimationTree");
+        // A player missing a part stands still instead of taking the game down
+        if let Err(error) = self.find_nodes(&owner) {
+            disable(&owner, error);
+            return;
+        }
 
+        let animation_tree = unsafe { self.animation_tree.assume_safe() };
         animation_tree.set_active(true);
 
-        // Access to HitboxPivot/SwordHitbox node
-        let sword_hitbox = owner
-            .get_node("HitboxPivot/SwordHitbox")
-            .expect("SwordHitbox node Should Exist");
-        let sword_hitbox = unsafe { sword_hitbox.assume_safe() };
-        let sword_hitbox = sword_hitbox
-            .cast::<Area2D>()
-            .expect("Node should cast to Area2D");
-
-        self.sword_hitbox = unsafe { sword_hitbox.assume_shared() };
+        // light1 -> light2 -> finisher
+        self.combo = ComboChain::new(
+            vec![
+                ComboStep {
+                    damage: 1,
+                    knockback: 1.0,
+                },
+                ComboStep {
+                    damage: 1,
+                    knockback: 1.0,
+                },
+                ComboStep {
+                    damage: 2,
+                    knockback: 1.5,
+                },
+            ],
+            self.attack_buffer_window,
+            self.attack_chain_window,
+        );
 
         // Set `knockback_vector` variable in HitboxPivot/SwordHitbox node
-        sword_hitbox.set("knockback_vector", self.roll_vector);
+        let sword_hitbox = unsafe { self.sword_hitbox.assume_safe() };
+        sword_hitbox.set("knockback_vector", self.context.motion.roll_vector);
 
-        // Access `PlayerStats` singleton
-        self.stats = owner
-            .get_node("../../../PlayerStats")
-            .expect("PlayerStats node Should Exist");
+        // Found by `SaveGame` when saving
+        owner.add_to_group("player", false);
+        if let Some(stats) = self.stats() {
+            save_game::restore_player(&owner, &stats.base());
+        }
 
-        let stats = unsafe { self.stats.assume_safe() };
+        // Read after the restore, so the saved weapon is the one in hand
+        self.update_weapon(&owner);
 
-        stats
-            .connect(
-                "no_health",
-                owner,
-                "queue_free",
-                VariantArray::new_shared(),
-                1,
-            )
-            .unwrap();
-
-        // Access `Hurtbox` node
-        self.hurtbox = owner
-            .get_node("Hurtbox")
-            .expect("Hurtbox node Should Exist");
-
-        // Loading scene
-        let player_hurt_sound_load = load_scene("res://Player/PlayerHurtSound.tscn");
-        match player_hurt_sound_load {
-            Some(_scene) => self.player_hurt_sound_load = _scene,
-            None => godot_print!("Could not load child scene. Check name."),
+        // Walked in through a door or warped here by `SceneManager`
+        if let Some(position) = scene_manager::take_spawn_position(&owner) {
+            owner.set_global_position(position);
         }
-
-        // Access `BlinkAnimationPlayer` node
-        self.blink_animation_player = owner
-            .get_node("BlinkAnimationPlayer")
-            .expect("BlinkAnimationPlayer node Should Exist");
+        self.respawn_position = owner.global_position();
     }
 
     // Called during the physics processing step of the main loop.
     // Physics processing means that the frame rate is synced to the physics, i.e. the delta variable should be constant.
     #[export]
     fn _physics_process(&mut self, owner: &KinematicBody2D, delta: f64) {
-        // Access to AnimationTree node
-        let animation_tree = owner.get_node("AnimationTree").expect("Node Should Exist");
-        let animation_tree = unsafe { animation_tree.assume_safe() };
-        let animation_tree = animation_tree
-            .cast::<AnimationTree>()
-            .expect("Node should cast to AnimationTree");
-
-        // Access to Animation State AnimationNodeStateMachinePlayback inside AnimationTree node
-        let animation_state = animation_tree.get("parameters/playback");
-        let animation_state = animation_state
-            .try_to_object::<AnimationNodeStateMachinePlayback>()
-            .expect("cast should be valid");
+        // Both found in `_ready`. The playback is a reference, a local one keeps
+        // `self` free for the state functions
+        let animation_tree = unsafe { self.animation_tree.assume_safe() };
+        let animation_state = self.animation_state.clone();
         let animation_state = unsafe { animation_state.assume_safe() };
 
-        match self.state {
+        self.combo.tick(delta as f32);
+
+        match self.state_machine.state() {
             PlayerState::MOVE => self.move_state(owner, delta, animation_tree, animation_state),
             PlayerState::ROLL => self.roll_state(owner, delta, animation_state),
             PlayerState::ATTACK => self.attack_state(owner, delta, animation_state),
+            PlayerState::HURT => self.hurt_state(owner, delta, animation_state),
+            PlayerState::DEAD | PlayerState::INTERACT => animation_state.travel("Idle"),
         }
     }
 
     #[export]
-    fn attack_animation_finished(&mut self, _owner: &KinematicBody2D) {
-        self.state = PlayerState::MOVE;
+    fn attack_animation_finished(&mut self, owner: &KinematicBody2D) {
+        // A hit during the swing already left the attack, the stagger ends on its own
+        if self.state_machine.state() != PlayerState::ATTACK {
+            return;
+        }
+
+        // A buffered press chains straight into the next swing
+        if let Some(step) = self.combo.finish() {
+            if self.consume_stamina(self.attack_stamina_cost) {
+                self.apply_combo_step(owner, step);
+                self.attack_restart = true;
+                return;
+            }
+
+            self.combo.cancel();
+        }
+
+        self.fire(PlayerEvent::Finished);
     }
 
     #[export]
     fn roll_animation_finished(&mut self, _owner: &KinematicBody2D) {
-        self.velocity *= 0.8;
-        self.state = PlayerState::MOVE;
+        if self.state_machine.state() == PlayerState::ROLL {
+            self.fire(PlayerEvent::Finished);
+        }
     }
 
+    // Called by interactables once their interaction is done
     #[export]
-    fn _on_hurtbox_area_entered(&self, owner: &KinematicBody2D, area: Ref<Area2D>) {
-        let stats = unsafe { self.stats.assume_safe() };
-        let area = unsafe { area.assume_safe() };
+    fn interaction_finished(&mut self, _owner: &KinematicBody2D) {
+        if self.state_machine.state() == PlayerState::INTERACT {
+            self.fire(PlayerEvent::Finished);
+        }
+    }
 
-        // Update `health` variable in `Stats` node
-        let health = (stats.get("health").to_i64() - area.get("damage").to_i64()).to_variant();
+    // Called by interactables when the player walks in or out of their range
+    #[export]
+    fn set_can_interact(&mut self, _owner: &KinematicBody2D, value: bool) {
+        self.context.can_interact = value;
+    }
 
-        unsafe {
-            stats.call("set_health", &[health]);
+    // Accepting signal from the `Health` node
+    #[export]
+    fn _on_health_damaged(&mut self, _owner: &KinematicBody2D, _damage: DamageInfo) {
+        // Disabled in `_ready`, nothing to react with
+        let health = match self.stats() {
+            Some(stats) => stats.map(|stats, _| stats.health()).unwrap_or(0),
+            None => return,
+        };
+
+        // A killing blow is handled on `no_health`
+        if health > 0 && self.fire(PlayerEvent::Hurt).is_some() {
+            self.stagger_time = self.stagger_duration;
         }
 
+        self.combo.cancel();
+    }
+
+    // Accepting signal from the `PlayerStats` singleton
+    #[export]
+    fn _on_stats_no_health(&mut self, owner: &KinematicBody2D) {
+        if self.fire(PlayerEvent::Died).is_none() {
+            return;
+        }
+
+        self.combo.cancel();
+
+        // Nothing can hit the player until it respawns
         let hurtbox = unsafe { self.hurtbox.assume_safe() };
-        unsafe { hurtbox.call("start_invincibility", &[(0.5).to_variant()]) };
-        unsafe { hurtbox.call("create_hit_effect", &[]) };
-
-        let player_hurt_sound = unsafe { self.player_hurt_sound_load.assume_safe() };
-        let player_hurt_sound = player_hurt_sound
-            .instance(PackedScene::GEN_EDIT_STATE_DISABLED)
-            .expect("should be able to instance scene");
-
-        unsafe {
-            owner
-                .get_tree()
-                .unwrap()
-                .assume_safe()
-                .current_scene()
-                .unwrap()
-                .assume_safe()
-                .add_child(player_hurt_sound, false)
+        hurtbox.set_deferred("monitoring", false.to_variant());
+
+        play_animation(&self.death_animation_player, "die");
+        owner.emit_signal("died", &[]);
+    }
+
+    // Back on its feet with full health and stamina, at the active checkpoint
+    // or else at the respawn position
+    #[export]
+    fn respawn(&mut self, owner: &KinematicBody2D) {
+        // Disabled in `_ready`, it never died either
+        let stats = match self.stats() {
+            Some(stats) => stats,
+            None => return,
         };
+
+        let position = checkpoint::active_position(owner).unwrap_or(self.respawn_position);
+        owner.set_global_position(position);
+
+        stats
+            .map_mut(|stats, owner| {
+                stats.set_health(&owner, stats.max_health());
+                stats.set_stamina(&owner, stats.max_stamina());
+            })
+            .expect("PlayerStats should not be borrowed");
+
+        self.context.motion.velocity = Vector2::zero();
+        self.combo.cancel();
+
+        if self.fire(PlayerEvent::Revived).is_some() {
+            let hurtbox = unsafe { self.hurtbox.assume_safe() };
+            hurtbox.set_deferred("monitoring", true.to_variant());
+            unsafe {
+                hurtbox.call(
+                    "start_invincibility",
+                    &[self.respawn_invincibility.to_variant()],
+                )
+            };
+
+            play_animation(&self.death_animation_player, "revive");
+        }
+
+        owner.emit_signal("respawned", &[]);
     }
 
     #[export]
-    fn _on_hurtbox_invincibility_started(&self, _owner: &KinematicBody2D) {
-        let blink_animation_player = unsafe { self.blink_animation_player.assume_safe() };
-        let blink_animation_player = blink_animation_player.cast::<AnimationPlayer>().unwrap();
+    fn set_respawn_position(&mut self, _owner: &KinematicBody2D, position: Vector2) {
+        self.respawn_position = position;
+    }
 
-        blink_animation_player.play("start", -1.0, 1.0, false)
+    // Accepting signal from the `Inventory` singleton
+    #[export]
+    fn _on_inventory_weapon_changed(
+        &mut self,
+        _owner: &KinematicBody2D,
+        damage: i64,
+        knockback: f32,
+    ) {
+        self.weapon = WeaponStats { damage, knockback };
     }
 
     #[export]
-    fn _on_hurtbox_invincibility_ended(&self, _owner: &KinematicBody2D) {
-        let blink_animation_player = unsafe { self.blink_animation_player.assume_safe() };
-        let blink_animation_player = blink_animation_player.cast::<AnimationPlayer>().unwrap();
+    fn _on_hurtbox_invincibility_started(&self, _owner: &KinematicBody2D) {
+        play_animation(&self.blink_animation_player, "start");
+    }
 
-        blink_animation_player.play("stop", -1.0, 1.0, false)
+    #[export]
+    fn _on_hurtbox_invincibility_ended(&self, _owner: &KinematicBody2D) {
+        play_animation(&self.blink_animation_player, "stop");
     }
 }
 
 impl Player {
+    fn find_nodes(&mut self, owner: &KinematicBody2D) -> Result<(), NodeError> {
+        let animation_tree = get_typed::<AnimationTree>(owner, "AnimationTree")?;
+        // Access to Animation State AnimationNodeStateMachinePlayback inside AnimationTree node
+        self.animation_state = animation_tree
+            .get("parameters/playback")
+            .try_to_object::<AnimationNodeStateMachinePlayback>()
+            .ok_or_else(|| {
+                NodeError::new(
+                    owner,
+                    "AnimationTree:parameters/playback",
+                    AnimationNodeStateMachinePlayback::class_name(),
+                    None,
+                )
+            })?;
+        self.animation_tree = animation_tree.claim();
+        self.stats = Some(get_instance::<Stats>(owner, "/root/PlayerStats")?);
+        self.sword_hitbox = get_ref::<Area2D>(owner, "HitboxPivot/SwordHitbox")?;
+        self.hurtbox = get_ref::<Area2D>(owner, "Hurtbox")?;
+        self.blink_animation_player = get_ref::<AnimationPlayer>(owner, "BlinkAnimationPlayer")?;
+        self.death_animation_player = get_ref::<AnimationPlayer>(owner, "DeathAnimationPlayer")?;
+
+        Ok(())
+    }
+
+    // `PlayerStats`, None once `_ready` disabled the player
+    fn stats(&self) -> Option<RefInstance<'_, Stats, Shared>> {
+        self.stats
+            .as_ref()
+            .map(|stats| unsafe { stats.assume_safe() })
+    }
+
+    // Spends `amount` stamina, false without enough of it or without `PlayerStats`
+    fn consume_stamina(&self, amount: f64) -> bool {
+        self.stats()
+            .and_then(|stats| {
+                stats
+                    .map_mut(|stats, owner| stats.consume_stamina(&owner, amount))
+                    .ok()
+            })
+            .unwrap_or(false)
+    }
+
+    fn fire(&mut self, event: PlayerEvent) -> Option<PlayerState> {
+        self.state_machine.fire(event, &mut self.context)
+    }
+
+    // Fires `event` only if it leads somewhere and there is enough stamina to pay for it
+    fn try_action(&mut self, event: PlayerEvent, stamina_cost: f64) -> bool {
+        if self
+            .state_machine
+            .next_state(event, &self.context)
+            .is_none()
+        {
+            return false;
+        }
+
+        self.consume_stamina(stamina_cost) && self.fire(event).is_some()
+    }
+
+    fn motor(&self) -> PlayerMotor {
+        PlayerMotor::new(
+            self.acceleration,
+            self.effective_max_speed(),
+            self.friction,
+            self.roll_speed,
+        )
+    }
+
+    // `max_speed` after slows and stuns on `PlayerStats`
+    fn effective_max_speed(&self) -> f32 {
+        let multiplier = self
+            .stats()
+            .and_then(|stats| {
+                stats
+                    .map(|stats, owner| stats.get_speed_multiplier(&owner))
+                    .ok()
+            })
+            .unwrap_or(1.0);
+
+        self.max_speed * multiplier
+    }
+
+    fn is_stunned(&self) -> bool {
+        self.stats()
+            .and_then(|stats| stats.map(|stats, owner| stats.is_stunned(&owner)).ok())
+            .unwrap_or(false)
+    }
+
     fn move_state(
         &mut self,
         owner: &KinematicBody2D,
@@ -210,57 +464,79 @@ impl Player {
         animation_tree: TRef<AnimationTree>,
         animation_state: TRef<AnimationNodeStateMachinePlayback>,
     ) {
+        // Stunned players can't move or act
+        if self.is_stunned() {
+            self.context.motion =
+                self.motor()
+                    .move_step(self.context.motion, Vector2::zero(), delta as f32);
+            animation_state.travel("Idle");
+            self.player_move(owner);
+            return;
+        }
+
         let input = Input::godot_singleton();
-        self.input_vector = Vector2::zero();
 
-        self.input_vector.x = Input::get_action_strength(input, "ui_right") as f32
-            - Input::get_action_strength(input, "ui_left") as f32;
-        self.input_vector.y = Input::get_action_strength(input, "ui_down") as f32
-            - Input::get_action_strength(input, "ui_up") as f32;
+        let input_vector = Vector2::new(
+            Input::get_action_strength(input, "ui_right") as f32
+                - Input::get_action_strength(input, "ui_left") as f32,
+            Input::get_action_strength(input, "ui_down") as f32
+                - Input::get_action_strength(input, "ui_up") as f32,
+        );
 
-        self.input_vector = normalized(self.input_vector);
+        self.context.motion =
+            self.motor()
+                .move_step(self.context.motion, input_vector, delta as f32);
 
-        if self.input_vector != Vector2::zero() {
-            self.roll_vector = self.input_vector;
+        if input_vector != Vector2::zero() {
+            let facing = self.context.motion.roll_vector;
 
             // Set `knockback_vector` variable in HitboxPivot/SwordHitbox node
             let sword_hitbox = unsafe { self.sword_hitbox.assume_safe() };
-            sword_hitbox.set("knockback_vector", self.input_vector);
+            sword_hitbox.set("knockback_vector", facing);
 
-            animation_tree.set("parameters/Idle/blend_position", self.input_vector);
-            animation_tree.set("parameters/Run/blend_position", self.input_vector);
-            animation_tree.set("parameters/Attack/blend_position", self.input_vector);
-            animation_tree.set("parameters/Roll/blend_position", self.input_vector);
+            animation_tree.set("parameters/Idle/blend_position", facing);
+            animation_tree.set("parameters/Run/blend_position", facing);
+            animation_tree.set("parameters/Attack/blend_position", facing);
+            animation_tree.set("parameters/Roll/blend_position", facing);
 
             animation_state.travel("Run");
-
-            self.velocity = self.velocity.move_towards(
-                self.input_vector * self.max_speed,
-                self.acceleration * delta as f32,
-            );
         } else {
             animation_state.travel("Idle");
-
-            self.velocity = self
-                .velocity
-                .move_towards(Vector2::zero(), self.friction * delta as f32);
         }
 
         self.player_move(owner);
 
-        if Input::is_action_just_pressed(input, "roll") {
-            self.state = PlayerState::ROLL;
+        if Input::is_action_just_pressed(input, "roll")
+            && self.try_action(PlayerEvent::RollPressed, self.roll_stamina_cost)
+        {
+            // Invincibility frames for the first part of the roll
+            let hurtbox = unsafe { self.hurtbox.assume_safe() };
+            unsafe {
+                hurtbox.call(
+                    "start_invincibility",
+                    &[self.roll_invincibility_duration.to_variant()],
+                )
+            };
+
+            audio_manager::play_sfx(owner, "evade", owner.global_position());
+        }
+
+        if Input::is_action_just_pressed(input, "attack")
+            && self.try_action(PlayerEvent::AttackPressed, self.attack_stamina_cost)
+        {
+            let step = self.combo.start();
+            self.apply_combo_step(owner, step);
         }
 
-        if Input::is_action_just_pressed(input, "attack") {
-            self.state = PlayerState::ATTACK;
+        if Input::is_action_just_pressed(input, "interact") {
+            self.fire(PlayerEvent::InteractPressed);
         }
     }
 
     fn player_move(&mut self, owner: &KinematicBody2D) {
-        self.velocity = KinematicBody2D::move_and_slide(
+        self.context.motion.velocity = KinematicBody2D::move_and_slide(
             owner,
-            self.velocity,
+            self.context.motion.velocity,
             Vector2::zero(),
             false,
             4,
@@ -275,7 +551,7 @@ impl Player {
         _delta: f64,
         animation_state: TRef<AnimationNodeStateMachinePlayback>,
     ) {
-        self.velocity = self.roll_vector * self.roll_speed;
+        self.context.motion = self.motor().roll_step(self.context.motion);
         animation_state.travel("Roll");
 
         self.player_move(owner);
@@ -287,7 +563,67 @@ impl Player {
         _delta: f64,
         animation_state: TRef<AnimationNodeStateMachinePlayback>,
     ) {
-        self.velocity = Vector2::zero();
-        animation_state.travel("Attack");
+        self.context.motion = self.motor().attack_step(self.context.motion);
+
+        // Presses during the swing are kept for a short window
+        if Input::is_action_just_pressed(Input::godot_singleton(), "attack") {
+            self.combo.buffer_press();
+        }
+
+        if self.attack_restart {
+            self.attack_restart = false;
+            animation_state.start("Attack");
+        } else {
+            animation_state.travel("Attack");
+        }
+    }
+
+    fn update_weapon(&mut self, owner: &KinematicBody2D) {
+        self.weapon = match get_instance::<Inventory>(owner, "/root/Inventory") {
+            Ok(inventory) => unsafe { inventory.assume_safe() }
+                .map(|inventory, _| inventory.weapon_stats())
+                .unwrap_or_default(),
+            Err(error) => {
+                godot_print!("{}, fighting unarmed", error);
+                WeaponStats::default()
+            }
+        };
+    }
+
+    fn apply_combo_step(&self, owner: &KinematicBody2D, step: ComboStep) {
+        let sword_hitbox = unsafe { self.sword_hitbox.assume_safe() };
+        sword_hitbox.set("damage", step.damage + self.weapon.damage);
+        sword_hitbox.set(
+            "knockback_vector",
+            self.context.motion.roll_vector * step.knockback * self.weapon.knockback,
+        );
+
+        audio_manager::play_sfx(owner, "swipe", owner.global_position());
     }
+
+    fn hurt_state(
+        &mut self,
+        owner: &KinematicBody2D,
+        delta: f64,
+        animation_state: TRef<AnimationNodeStateMachinePlayback>,
+    ) {
+        animation_state.travel("Idle");
+
+        // Slow down to a stop while staggered
+        self.context.motion =
+            self.motor()
+                .move_step(self.context.motion, Vector2::zero(), delta as f32);
+        self.player_move(owner);
+
+        self.stagger_time -= delta as f32;
+        if self.stagger_time <= 0.0 {
+            self.fire(PlayerEvent::Finished);
+        }
+    }
+}
+
+fn play_animation(animation_player: &Ref<AnimationPlayer>, name: &str) {
+    let animation_player = unsafe { animation_player.assume_safe() };
+
+    animation_player.play(name, -1.0, 1.0, false)
 }
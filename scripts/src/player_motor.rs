@@ -0,0 +1,152 @@
+use gdnative::prelude::*;
+
+use crate::utils::normalized;
+
+// Velocity and facing of the player between two physics steps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MotorState {
+    pub velocity: Vector2,
+    pub roll_vector: Vector2,
+}
+
+impl Default for MotorState {
+    fn default() -> Self {
+        MotorState {
+            velocity: Vector2::zero(),
+            roll_vector: Vector2::new(0.0, 1.0),
+        }
+    }
+}
+
+// Engine-independent movement rules of the player.
+// `Player` only feeds it input and delta, then hands the velocity to `move_and_slide`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlayerMotor {
+    pub acceleration: f32,
+    pub max_speed: f32,
+    pub friction: f32,
+    pub roll_speed: f32,
+}
+
+impl PlayerMotor {
+    pub fn new(acceleration: f32, max_speed: f32, friction: f32, roll_speed: f32) -> Self {
+        PlayerMotor {
+            acceleration,
+            max_speed,
+            friction,
+            roll_speed,
+        }
+    }
+
+    // Accelerates towards the input direction or slows down by friction when there is no input.
+    pub fn move_step(&self, state: MotorState, input_vector: Vector2, delta: f32) -> MotorState {
+        let input_vector = normalized(input_vector);
+
+        if input_vector != Vector2::zero() {
+            MotorState {
+                velocity: state
+                    .velocity
+                    .move_towards(input_vector * self.max_speed, self.acceleration * delta),
+                roll_vector: input_vector,
+            }
+        } else {
+            MotorState {
+                velocity: state
+                    .velocity
+                    .move_towards(Vector2::zero(), self.friction * delta),
+                ..state
+            }
+        }
+    }
+
+    // Rolls in the last facing direction at full roll speed.
+    pub fn roll_step(&self, state: MotorState) -> MotorState {
+        MotorState {
+            velocity: state.roll_vector * self.roll_speed,
+            ..state
+        }
+    }
+
+    // The player stands still while swinging.
+    pub fn attack_step(&self, state: MotorState) -> MotorState {
+        MotorState {
+            velocity: Vector2::zero(),
+            ..state
+        }
+    }
+
+    // Keeps some momentum when the roll animation ends.
+    pub fn end_roll(state: MotorState) -> MotorState {
+        MotorState {
+            velocity: state.velocity * 0.8,
+            ..state
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn motor() -> PlayerMotor {
+        PlayerMotor::new(500.0, 80.0, 500.0, 120.0)
+    }
+
+    #[test]
+    fn move_step_accelerates_up_to_max_speed() {
+        let state = motor().move_step(MotorState::default(), Vector2::new(1.0, 0.0), 0.1);
+        assert_eq!(state.velocity, Vector2::new(50.0, 0.0));
+        assert_eq!(state.roll_vector, Vector2::new(1.0, 0.0));
+
+        let state = motor().move_step(state, Vector2::new(1.0, 0.0), 0.1);
+        assert_eq!(state.velocity, Vector2::new(80.0, 0.0));
+    }
+
+    #[test]
+    fn move_step_without_input_slows_down_to_zero() {
+        let state = MotorState {
+            velocity: Vector2::new(0.0, 80.0),
+            roll_vector: Vector2::new(0.0, 1.0),
+        };
+
+        let state = motor().move_step(state, Vector2::zero(), 0.1);
+        assert_eq!(state.velocity, Vector2::new(0.0, 30.0));
+
+        let state = motor().move_step(state, Vector2::zero(), 0.1);
+        assert_eq!(state.velocity, Vector2::zero());
+        // Still facing the same way for the next roll
+        assert_eq!(state.roll_vector, Vector2::new(0.0, 1.0));
+    }
+
+    #[test]
+    fn roll_step_uses_roll_vector() {
+        let state = MotorState {
+            velocity: Vector2::new(10.0, 0.0),
+            roll_vector: Vector2::new(-1.0, 0.0),
+        };
+
+        let state = motor().roll_step(state);
+        assert_eq!(state.velocity, Vector2::new(-120.0, 0.0));
+        assert_eq!(state.roll_vector, Vector2::new(-1.0, 0.0));
+    }
+
+    #[test]
+    fn attack_step_stops_the_player() {
+        let state = MotorState {
+            velocity: Vector2::new(40.0, 40.0),
+            roll_vector: Vector2::new(1.0, 0.0),
+        };
+
+        let state = motor().attack_step(state);
+        assert_eq!(state.velocity, Vector2::zero());
+        assert_eq!(state.roll_vector, Vector2::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn end_roll_keeps_some_momentum() {
+        let state = motor().roll_step(MotorState::default());
+        let state = PlayerMotor::end_roll(state);
+
+        assert_eq!(state.velocity, Vector2::new(0.0, 96.0));
+    }
+}
@@ -0,0 +1,114 @@
+use gdnative::prelude::*;
+
+use crate::player_motor::*;
+use crate::state_machine::StateMachine;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlayerState {
+    MOVE,
+    ROLL,
+    ATTACK,
+    HURT,
+    DEAD,
+    INTERACT,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlayerEvent {
+    RollPressed,
+    AttackPressed,
+    InteractPressed,
+    // Roll/attack animation, stagger or interaction is over
+    Finished,
+    Hurt,
+    Died,
+    // Respawned after dying
+    Revived,
+}
+
+// Everything the player transition rules look at or change.
+pub struct PlayerContext {
+    pub motion: MotorState,
+    pub can_interact: bool,
+}
+
+pub type PlayerStateMachine = StateMachine<PlayerState, PlayerEvent, PlayerContext>;
+
+pub fn player_state_machine() -> PlayerStateMachine {
+    use PlayerEvent::*;
+    use PlayerState::*;
+
+    let mut machine = PlayerStateMachine::new(MOVE);
+
+    machine.add_transition(MOVE, RollPressed, ROLL, None);
+    machine.add_transition(MOVE, AttackPressed, ATTACK, None);
+    machine.add_transition(MOVE, InteractPressed, INTERACT, Some(|c| c.can_interact));
+
+    machine.add_transition(ROLL, Finished, MOVE, None);
+    machine.add_transition(ATTACK, Finished, MOVE, None);
+    machine.add_transition(HURT, Finished, MOVE, None);
+    machine.add_transition(INTERACT, Finished, MOVE, None);
+
+    machine.add_transition(MOVE, Hurt, HURT, None);
+    machine.add_transition(ROLL, Hurt, HURT, None);
+    machine.add_transition(ATTACK, Hurt, HURT, None);
+    machine.add_transition(INTERACT, Hurt, HURT, None);
+
+    machine.add_any_transition(Died, DEAD, None);
+    machine.add_transition(DEAD, Revived, MOVE, None);
+
+    // Keep some momentum when a roll ends
+    machine.add_exit_hook(ROLL, |c| c.motion = PlayerMotor::end_roll(c.motion));
+
+    // Being hit or dying stops the player in place
+    machine.add_enter_hook(HURT, |c| c.motion.velocity = Vector2::zero());
+    machine.add_enter_hook(DEAD, |c| c.motion.velocity = Vector2::zero());
+    machine.add_enter_hook(INTERACT, |c| c.motion.velocity = Vector2::zero());
+
+    machine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finishing_a_roll_keeps_some_momentum() {
+        let mut machine = player_state_machine();
+        let mut context = PlayerContext {
+            motion: MotorState {
+                velocity: Vector2::new(100.0, 0.0),
+                roll_vector: Vector2::new(1.0, 0.0),
+            },
+            can_interact: false,
+        };
+
+        machine.fire(PlayerEvent::RollPressed, &mut context);
+        assert_eq!(
+            machine.fire(PlayerEvent::Finished, &mut context),
+            Some(PlayerState::MOVE)
+        );
+        assert_eq!(context.motion.velocity, Vector2::new(80.0, 0.0));
+    }
+
+    #[test]
+    fn interact_needs_something_to_interact_with() {
+        let mut machine = player_state_machine();
+        let mut context = PlayerContext {
+            motion: MotorState::default(),
+            can_interact: false,
+        };
+
+        assert_eq!(
+            machine.fire(PlayerEvent::InteractPressed, &mut context),
+            None
+        );
+
+        context.can_interact = true;
+        assert_eq!(
+            machine.fire(PlayerEvent::InteractPressed, &mut context),
+            Some(PlayerState::INTERACT)
+        );
+    }
+}
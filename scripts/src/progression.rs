@@ -0,0 +1,215 @@
+use crate::modifiers::*;
+
+// XP curve: going from `level` to `level + 1` takes `base * level^exponent` XP.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XpCurve {
+    pub base: f64,
+    pub exponent: f64,
+    pub max_level: u32,
+}
+
+impl XpCurve {
+    // 0 at `max_level`, where XP stops counting
+    pub fn xp_to_next(&self, level: u32) -> u32 {
+        if level >= self.max_level {
+            return 0;
+        }
+
+        ((self.base * (level as f64).powf(self.exponent)).round() as u32).max(1)
+    }
+}
+
+// Level and XP towards the next one.
+pub struct Progression {
+    curve: XpCurve,
+    level: u32,
+    xp: u32,
+}
+
+impl Progression {
+    pub fn new(curve: XpCurve) -> Self {
+        Progression {
+            curve,
+            level: 1,
+            xp: 0,
+        }
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn xp(&self) -> u32 {
+        self.xp
+    }
+
+    pub fn xp_to_next(&self) -> u32 {
+        self.curve.xp_to_next(self.level)
+    }
+
+    // Adds XP, carrying the excess over several levels, and returns the levels gained.
+    pub fn add_xp(&mut self, amount: u32) -> u32 {
+        let start = self.level;
+        self.xp = self.xp.saturating_add(amount);
+
+        loop {
+            let needed = self.xp_to_next();
+
+            if needed == 0 {
+                self.xp = 0;
+                break;
+            }
+
+            if self.xp < needed {
+                break;
+            }
+
+            self.xp -= needed;
+            self.level += 1;
+        }
+
+        self.level - start
+    }
+
+    // Sets level and XP, e.g. from a save file, keeping them within the curve
+    pub fn restore(&mut self, level: u32, xp: u32) {
+        self.level = num::clamp(level, 1, self.curve.max_level.max(1));
+        self.xp = 0;
+        self.add_xp(xp);
+    }
+}
+
+// Flat modifiers, sourced "level", for the growth of every level past the first.
+// `growth` is a gain per level for each stat.
+pub fn growth_modifiers(growth: &[(StatKind, f64)], level: u32) -> Vec<StatModifier> {
+    let levels = level.saturating_sub(1) as f64;
+
+    growth
+        .iter()
+        .map(|(stat, gain)| StatModifier {
+            stat: *stat,
+            op: ModifierOp::Flat,
+            value: gain * levels,
+            source: "level".to_string(),
+            duration: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve() -> XpCurve {
+        XpCurve {
+            base: 10.0,
+            exponent: 1.5,
+            max_level: 4,
+        }
+    }
+
+    #[test]
+    fn xp_to_next_grows_with_the_level() {
+        let curve = curve();
+
+        assert_eq!(curve.xp_to_next(1), 10);
+        assert_eq!(curve.xp_to_next(2), 28);
+        assert_eq!(curve.xp_to_next(3), 52);
+        assert_eq!(curve.xp_to_next(4), 0);
+    }
+
+    #[test]
+    fn xp_below_the_next_level_is_kept() {
+        let mut progression = Progression::new(curve());
+
+        assert_eq!(progression.add_xp(7), 0);
+        assert_eq!(progression.level(), 1);
+        assert_eq!(progression.xp(), 7);
+    }
+
+    #[test]
+    fn one_gain_can_carry_over_several_levels() {
+        let mut progression = Progression::new(curve());
+
+        // 10 to level 2, 28 to level 3, 5 left over
+        assert_eq!(progression.add_xp(43), 2);
+        assert_eq!(progression.level(), 3);
+        assert_eq!(progression.xp(), 5);
+        assert_eq!(progression.xp_to_next(), 52);
+    }
+
+    #[test]
+    fn gains_add_up() {
+        let mut progression = Progression::new(curve());
+
+        progression.add_xp(6);
+        assert_eq!(progression.add_xp(6), 1);
+        assert_eq!(progression.level(), 2);
+        assert_eq!(progression.xp(), 2);
+    }
+
+    #[test]
+    fn the_max_level_caps_levels_and_xp() {
+        let mut progression = Progression::new(curve());
+
+        assert_eq!(progression.add_xp(1000), 3);
+        assert_eq!(progression.level(), 4);
+        assert_eq!(progression.xp(), 0);
+
+        assert_eq!(progression.add_xp(50), 0);
+        assert_eq!(progression.level(), 4);
+        assert_eq!(progression.xp(), 0);
+    }
+
+    #[test]
+    fn restore_keeps_level_and_xp_within_the_curve() {
+        let mut progression = Progression::new(curve());
+
+        progression.restore(2, 5);
+        assert_eq!((progression.level(), progression.xp()), (2, 5));
+
+        progression.restore(9, 5);
+        assert_eq!((progression.level(), progression.xp()), (4, 0));
+
+        progression.restore(0, 12);
+        assert_eq!((progression.level(), progression.xp()), (2, 2));
+    }
+
+    #[test]
+    fn growth_is_nothing_at_level_one() {
+        let modifiers = growth_modifiers(&[(StatKind::MaxHealth, 1.0)], 1);
+
+        assert_eq!(modifiers.len(), 1);
+        assert_eq!(modifiers[0].value, 0.0);
+    }
+
+    #[test]
+    fn growth_is_applied_for_every_level_past_the_first() {
+        let growth = [(StatKind::MaxHealth, 1.0), (StatKind::Attack, 0.5)];
+
+        let mut stack = ModifierStack::default();
+        for modifier in growth_modifiers(&growth, 5) {
+            stack.add(modifier);
+        }
+
+        assert_eq!(stack.value(StatKind::MaxHealth, 4.0), 8.0);
+        assert_eq!(stack.value(StatKind::Attack, 1.0), 3.0);
+        assert_eq!(stack.value(StatKind::Defense, 1.0), 1.0);
+    }
+
+    #[test]
+    fn growth_replaces_the_previous_level() {
+        let mut stack = ModifierStack::default();
+        let growth = [(StatKind::MaxHealth, 2.0)];
+
+        for modifier in growth_modifiers(&growth, 2) {
+            stack.add(modifier);
+        }
+        stack.remove_source("level");
+        for modifier in growth_modifiers(&growth, 3) {
+            stack.add(modifier);
+        }
+
+        assert_eq!(stack.value(StatKind::MaxHealth, 4.0), 8.0);
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use rand::SeedableRng;
+use rand_pcg::Pcg64;
+
+// Independent random streams derived from one seed.
+// A stream only depends on the seed and its name, so an entity draws the same
+// numbers no matter in which order entities are created or updated.
+pub struct RngStreams {
+    seed: u64,
+    streams: HashMap<String, Pcg64>,
+}
+
+impl RngStreams {
+    pub fn new(seed: u64) -> Self {
+        RngStreams {
+            seed,
+            streams: HashMap::new(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // Restarts every stream from the new seed
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.streams.clear();
+    }
+
+    pub fn stream(&mut self, name: &str) -> &mut Pcg64 {
+        let seed = self.seed;
+
+        self.streams
+            .entry(name.to_string())
+            .or_insert_with(|| Pcg64::seed_from_u64(seed ^ hash_name(name)))
+    }
+
+    // Drops a stream, it restarts from the seed when asked for again
+    pub fn remove(&mut self, name: &str) {
+        self.streams.remove(name);
+    }
+}
+
+// FNV-1a, stable across platforms and Rust versions unlike `DefaultHasher`
+fn hash_name(name: &str) -> u64 {
+    name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn draw(streams: &mut RngStreams, name: &str) -> u64 {
+        streams.stream(name).gen()
+    }
+
+    #[test]
+    fn stream_only_depends_on_seed_and_name() {
+        let mut a = RngStreams::new(7);
+        let mut b = RngStreams::new(7);
+
+        draw(&mut a, "loot:World/Bat");
+        assert_eq!(draw(&mut a, "bat:World/Bat"), draw(&mut b, "bat:World/Bat"));
+        assert_ne!(
+            draw(&mut a, "bat:World/Bat2"),
+            draw(&mut b, "bat:World/Bat")
+        );
+    }
+
+    #[test]
+    fn removed_stream_restarts_from_seed() {
+        let mut streams = RngStreams::new(7);
+        let first = draw(&mut streams, "bat:World/Bat");
+        draw(&mut streams, "bat:World/Bat");
+
+        streams.remove("bat:World/Bat");
+        assert_eq!(draw(&mut streams, "bat:World/Bat"), first);
+        assert_eq!(streams.streams.len(), 1);
+    }
+}
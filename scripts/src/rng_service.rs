@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::sync::Once;
+
+use gdnative::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+
+use crate::rng::RngStreams;
+use crate::utils::{get_instance, node_id};
+
+const RNG_SERVICE_PATH: &str = "/root/RngService";
+
+// RngService "class".
+// Autoloaded singleton behind every random draw in the game. Setting `seed`
+// replays a whole session; with 0 a random seed is picked and printed.
+// The streams of a node are dropped when it leaves the tree, so spawned and
+// freed enemies do not pile up.
+#[derive(NativeClass)]
+#[inherit(Node)]
+pub struct RngService {
+    #[property(default = 0)]
+    seed: i64,
+
+    streams: RngStreams,
+    // Node id -> names of its streams
+    owners: HashMap<String, Vec<String>>,
+}
+
+#[gdnative::methods]
+impl RngService {
+    fn new(_owner: &Node) -> Self {
+        RngService {
+            seed: 0,
+
+            streams: RngStreams::new(0),
+            owners: HashMap::new(),
+        }
+    }
+
+    #[export]
+    fn _ready(&mut self, _owner: &Node) {
+        if self.seed == 0 {
+            self.seed = rand::thread_rng().gen();
+        }
+
+        self.streams.reseed(self.seed as u64);
+        godot_print!("RNG seed: {}", self.seed);
+    }
+
+    #[export]
+    fn set_seed(&mut self, _owner: &Node, seed: i64) {
+        self.seed = seed;
+        self.streams.reseed(seed as u64);
+    }
+
+    #[export]
+    fn get_seed(&self, _owner: &Node) -> i64 {
+        self.streams.seed() as i64
+    }
+
+    #[export]
+    fn randf_range(&mut self, _owner: &Node, stream: String, from: f64, to: f64) -> f64 {
+        self.streams.stream(&stream).gen_range(from..=to)
+    }
+
+    // Accepting signal from a node that used a stream
+    #[export]
+    fn _on_owner_tree_exiting(&mut self, _owner: &Node, id: String) {
+        for name in self.owners.remove(&id).unwrap_or_default() {
+            self.streams.remove(&name);
+        }
+    }
+}
+
+impl RngService {
+    // Streams restart from the seed once their node is gone, like a node that was
+    // never there. The signal is not deferred, a node entering with the same path
+    // right after must keep its new stream
+    fn track(&mut self, owner: TRef<Node>, node: &Node, id: String, name: &str) {
+        if !self.owners.contains_key(&id) {
+            let binds = VariantArray::new();
+            binds.push(id.to_variant());
+
+            node.connect(
+                "tree_exiting",
+                owner,
+                "_on_owner_tree_exiting",
+                binds.into_shared(),
+                0,
+            )
+            .unwrap();
+        }
+
+        let names = self.owners.entry(id).or_default();
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+}
+
+// Runs `f` with the `stream` sub-stream of `node`, e.g. `with_rng(owner, "bat", ..)`.
+// Without the `RngService` autoload, e.g. when running a single scene, or while it
+// is busy, `f` gets an unseeded generator instead.
+pub fn with_rng<R>(node: &Node, stream: &str, f: impl FnOnce(&mut Pcg64) -> R) -> R {
+    let id = node_id(node);
+    let name = format!("{}:{}", stream, id);
+
+    let rng_service = match get_instance::<RngService>(node, RNG_SERVICE_PATH) {
+        Ok(rng_service) => rng_service,
+        Err(error) => return unseeded(error.to_string(), f),
+    };
+    let rng_service = unsafe { rng_service.assume_safe() };
+
+    // A roll made while `RngService` is borrowed, e.g. from one of its own calls,
+    // falls back the same way
+    let mut f = Some(f);
+    let rolled = rng_service.map_mut(|rng_service, owner| {
+        let f = f.take()?;
+        rng_service.track(owner, node, id, &name);
+        Some(f(rng_service.streams.stream(&name)))
+    });
+
+    match (rolled, f) {
+        (Ok(Some(value)), _) => value,
+        (_, Some(f)) => unseeded("RngService is busy".to_string(), f),
+        (_, None) => unreachable!("`f` is only taken to be run"),
+    }
+}
+
+fn unseeded<R>(reason: String, f: impl FnOnce(&mut Pcg64) -> R) -> R {
+    static WARNING: Once = Once::new();
+    WARNING.call_once(|| godot_print!("{}, using an unseeded generator", reason));
+
+    f(&mut Pcg64::from_entropy())
+}
@@ -0,0 +1,256 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Bump when the layout changes and add the matching step to `MIGRATIONS`
+pub const SAVE_VERSION: u64 = 6;
+
+type Migration = fn(&mut Value);
+
+// `MIGRATIONS[n - 1]` upgrades a version `n` file to version `n + 1`
+const MIGRATIONS: [Migration; 5] = [
+    add_coins,
+    add_equipment,
+    add_level,
+    add_checkpoint,
+    add_scene,
+];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SaveData {
+    pub version: u64,
+    // `None` until the player has been saved once
+    pub player: Option<PlayerSave>,
+    pub world: WorldSave,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        SaveData {
+            version: SAVE_VERSION,
+            player: None,
+            world: WorldSave::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerSave {
+    pub health: i64,
+    // Before stat modifiers
+    pub max_health: i64,
+    // Scene the player was saved in, `position` is in that scene
+    pub scene: String,
+    pub position: [f32; 2],
+    pub level: u32,
+    pub xp: u32,
+    pub coins: i64,
+    // Item id -> count
+    pub inventory: BTreeMap<String, u32>,
+    // Equipment slot -> item id
+    pub equipment: BTreeMap<String, String>,
+}
+
+// Ids of world objects that are gone for good, e.g. cut grass and killed bats,
+// and of the checkpoint the player respawns at.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorldSave {
+    pub consumed: BTreeSet<String>,
+    pub checkpoint: Option<String>,
+}
+
+impl WorldSave {
+    pub fn consume(&mut self, id: &str) {
+        self.consumed.insert(id.to_string());
+    }
+
+    pub fn is_consumed(&self, id: &str) -> bool {
+        self.consumed.contains(id)
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Parse(serde_json::Error),
+    MissingVersion,
+    UnsupportedVersion(u64),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Parse(error) => write!(f, "invalid save file: {}", error),
+            SaveError::MissingVersion => write!(f, "save file has no version"),
+            SaveError::UnsupportedVersion(version) => {
+                write!(f, "unsupported save version {}", version)
+            }
+        }
+    }
+}
+
+pub fn to_json(data: &SaveData) -> String {
+    serde_json::to_string_pretty(data).expect("save data should serialize")
+}
+
+// Parses a save file of any supported version, migrating it to `SAVE_VERSION`
+pub fn from_json(text: &str) -> Result<SaveData, SaveError> {
+    let mut value: Value = serde_json::from_str(text).map_err(SaveError::Parse)?;
+    migrate(&mut value)?;
+
+    serde_json::from_value(value).map_err(SaveError::Parse)
+}
+
+// 1 -> 2: the player has a coin counter
+fn add_coins(value: &mut Value) {
+    if let Some(player) = value.get_mut("player").and_then(Value::as_object_mut) {
+        player.insert("coins".to_string(), Value::from(0));
+    }
+}
+
+// 2 -> 3: the player has equipment slots
+fn add_equipment(value: &mut Value) {
+    if let Some(player) = value.get_mut("player").and_then(Value::as_object_mut) {
+        player.insert("equipment".to_string(), Value::Object(Default::default()));
+    }
+}
+
+// 3 -> 4: the player has a level
+fn add_level(value: &mut Value) {
+    if let Some(player) = value.get_mut("player").and_then(Value::as_object_mut) {
+        player.insert("level".to_string(), Value::from(1));
+        player.insert("xp".to_string(), Value::from(0));
+    }
+}
+
+// 4 -> 5: the world remembers the active checkpoint
+fn add_checkpoint(value: &mut Value) {
+    if let Some(world) = value.get_mut("world").and_then(Value::as_object_mut) {
+        world.insert("checkpoint".to_string(), Value::Null);
+    }
+}
+
+// 5 -> 6: the game has more than one scene
+fn add_scene(value: &mut Value) {
+    if let Some(player) = value.get_mut("player").and_then(Value::as_object_mut) {
+        player.insert("scene".to_string(), Value::from("res://World.tscn"));
+    }
+}
+
+fn migrate(value: &mut Value) -> Result<(), SaveError> {
+    let mut version = value
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or(SaveError::MissingVersion)?;
+
+    if version == 0 || version > SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion(version));
+    }
+
+    while version < SAVE_VERSION {
+        MIGRATIONS[(version - 1) as usize](value);
+
+        version += 1;
+        value["version"] = Value::from(version);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save() -> SaveData {
+        let mut data = SaveData {
+            player: Some(PlayerSave {
+                health: 3,
+                max_health: 4,
+                scene: "res://World/Clearing.tscn".to_string(),
+                position: [12.0, -8.5],
+                level: 2,
+                xp: 15,
+                coins: 7,
+                inventory: vec![("potion".to_string(), 2)].into_iter().collect(),
+                equipment: vec![("weapon".to_string(), "sword".to_string())]
+                    .into_iter()
+                    .collect(),
+            }),
+            ..SaveData::default()
+        };
+        data.world.consume("Grass@World/Grass3");
+        data.world.checkpoint = Some("World/Checkpoint".to_string());
+
+        data
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = save();
+        assert_eq!(from_json(&to_json(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn migrates_version_1_to_current() {
+        let v1 = r#"{
+            "version": 1,
+            "player": {
+                "health": 3,
+                "max_health": 4,
+                "position": [1.0, 2.0],
+                "inventory": { "potion": 1 }
+            },
+            "world": { "consumed": ["Bat@World/Bat"] }
+        }"#;
+
+        let data = from_json(v1).unwrap();
+        assert_eq!(data.version, SAVE_VERSION);
+        assert!(data.world.is_consumed("Bat@World/Bat"));
+        assert_eq!(data.world.checkpoint, None);
+
+        let player = data.player.unwrap();
+        assert_eq!(player.health, 3);
+        assert_eq!(player.position, [1.0, 2.0]);
+        assert_eq!(player.coins, 0);
+        assert!(player.equipment.is_empty());
+        assert_eq!(player.level, 1);
+        assert_eq!(player.xp, 0);
+        assert_eq!(player.scene, "res://World.tscn");
+    }
+
+    #[test]
+    fn migrates_a_file_without_player() {
+        let v1 = r#"{ "version": 1, "player": null, "world": { "consumed": [] } }"#;
+        assert_eq!(from_json(v1).unwrap(), SaveData::default());
+    }
+
+    #[test]
+    fn rejects_future_and_missing_versions() {
+        let future = format!(
+            r#"{{ "version": {}, "player": null, "world": {{ "consumed": [] }} }}"#,
+            SAVE_VERSION + 1
+        );
+        assert!(matches!(
+            from_json(&future),
+            Err(SaveError::UnsupportedVersion(v)) if v == SAVE_VERSION + 1
+        ));
+
+        let missing = r#"{ "player": null, "world": { "consumed": [] } }"#;
+        assert!(matches!(from_json(missing), Err(SaveError::MissingVersion)));
+    }
+
+    #[test]
+    fn corrupted_json_is_an_error() {
+        let mut text = to_json(&save());
+        text.truncate(text.len() / 2);
+        assert!(matches!(from_json(&text), Err(SaveError::Parse(_))));
+
+        // Valid JSON with the wrong shape
+        let wrong = format!(
+            r#"{{ "version": {}, "player": 3, "world": {{}} }}"#,
+            SAVE_VERSION
+        );
+        assert!(matches!(from_json(&wrong), Err(SaveError::Parse(_))));
+    }
+}
@@ -0,0 +1,283 @@
+use gdnative::api::*;
+use gdnative::prelude::*;
+
+use crate::inventory::Inventory;
+use crate::save_data::*;
+use crate::scene_manager::{self, Spawn};
+use crate::stats::Stats;
+use crate::utils::{get_instance, node_id, read_text_file};
+
+const SAVE_GAME_PATH: &str = "/root/SaveGame";
+
+// SaveGame "class".
+// Autoloaded singleton holding the save file in memory. Loaded once on startup,
+// written on `save_game` and when the window is closed. The player is only
+// restored from it once per session, scene changes keep the autoloads as they are.
+#[derive(NativeClass)]
+#[inherit(Node)]
+pub struct SaveGame {
+    #[property]
+    path: String,
+
+    data: SaveData,
+    restored: bool,
+}
+
+#[gdnative::methods]
+impl SaveGame {
+    fn new(_owner: &Node) -> Self {
+        SaveGame {
+            path: "user://save.json".to_string(),
+
+            data: SaveData::default(),
+            restored: false,
+        }
+    }
+
+    #[export]
+    fn _ready(&mut self, _owner: &Node) {
+        self.data = self.load_data();
+    }
+
+    #[export]
+    fn _notification(&mut self, owner: &Node, what: i64) {
+        if what == Node::NOTIFICATION_WM_QUIT_REQUEST {
+            self.save_game(owner);
+        }
+    }
+
+    #[export]
+    pub fn save_game(&mut self, owner: &Node) -> bool {
+        self.capture_player(owner);
+
+        self.write_data()
+    }
+}
+
+impl SaveGame {
+    fn backup_path(&self) -> String {
+        format!("{}.bak", self.path)
+    }
+
+    // Falls back to the backup, then to a fresh save, if a file is missing or corrupted
+    fn load_data(&self) -> SaveData {
+        let paths = [self.path.clone(), self.backup_path()];
+
+        for path in paths.iter() {
+            let text = match read_text_file(path) {
+                Some(text) => text,
+                None => continue,
+            };
+
+            match from_json(&text) {
+                Ok(data) => return data,
+                Err(error) => {
+                    godot_print!("Could not load {}: {}", path, error);
+
+                    // Moved aside so the next save does not copy it over the backup
+                    let corrupted = format!("{}.corrupted", path);
+                    if Directory::new()
+                        .rename(path.as_str(), corrupted.as_str())
+                        .is_err()
+                    {
+                        godot_print!("Could not move {} to {}", path, corrupted);
+                    }
+                }
+            }
+        }
+
+        SaveData::default()
+    }
+
+    fn write_data(&self) -> bool {
+        let file = File::new();
+
+        if file.file_exists(self.path.as_str())
+            && Directory::new()
+                .copy(self.path.as_str(), self.backup_path().as_str())
+                .is_err()
+        {
+            godot_print!("Could not back up {}", self.path);
+        }
+
+        if let Err(error) = file.open(self.path.as_str(), File::WRITE) {
+            godot_print!("Could not open {}: {:?}", self.path, error);
+            return false;
+        }
+
+        file.store_string(to_json(&self.data));
+        file.close();
+
+        true
+    }
+
+    fn capture_player(&mut self, owner: &Node) {
+        let player = match find_player(owner) {
+            Some(player) => player,
+            None => return,
+        };
+
+        let (stats, inventory) = match (
+            get_instance::<Stats>(owner, "/root/PlayerStats"),
+            get_instance::<Inventory>(owner, "/root/Inventory"),
+        ) {
+            (Ok(stats), Ok(inventory)) => (stats, inventory),
+            (Err(error), _) | (_, Err(error)) => {
+                godot_print!("{}, the player is not saved", error);
+                return;
+            }
+        };
+
+        let (health, max_health, level, xp) = unsafe { stats.assume_safe() }
+            .map(|stats, _| {
+                (
+                    stats.health(),
+                    stats.base_max_health(),
+                    stats.level(),
+                    stats.xp(),
+                )
+            })
+            .expect("PlayerStats should not be borrowed");
+
+        // Keep the last save rather than one that would start the player dead
+        if health <= 0 {
+            return;
+        }
+
+        let (coins, inventory, equipment) = unsafe { inventory.assume_safe() }
+            .map(|inventory, _| (inventory.coins(), inventory.items(), inventory.equipment()))
+            .expect("Inventory should not be borrowed");
+
+        let scene = current_scene(owner).unwrap_or_default();
+        let position = player.global_position();
+
+        self.data.player = Some(PlayerSave {
+            health,
+            max_health,
+            scene,
+            position: [position.x, position.y],
+            level,
+            xp,
+            coins,
+            inventory,
+            equipment,
+        });
+    }
+
+    fn restore_player(&mut self, player: &Node2D, stats: &Node) {
+        if self.restored {
+            return;
+        }
+        self.restored = true;
+
+        let save = match &self.data.player {
+            Some(save) => save,
+            None => return,
+        };
+
+        unsafe { stats.call("set_max_health", &[save.max_health.to_variant()]) };
+
+        unsafe {
+            stats.call(
+                "set_progress",
+                &[save.level.to_variant(), save.xp.to_variant()],
+            )
+        };
+
+        // Level and equipment first, their bonuses can raise `max_health`
+        match get_instance::<Inventory>(player, "/root/Inventory") {
+            Ok(inventory) => unsafe { inventory.assume_safe() }
+                .map_mut(|inventory, owner| {
+                    inventory.restore(&owner, save.coins, save.inventory.clone(), &save.equipment)
+                })
+                .expect("Inventory should not be borrowed"),
+            Err(error) => godot_print!("{}, items are not restored", error),
+        }
+
+        unsafe { stats.call("set_health", &[save.health.to_variant()]) };
+
+        let position = Vector2::new(save.position[0], save.position[1]);
+        if current_scene(player).as_deref() == Some(save.scene.as_str()) {
+            player.set_global_position(position);
+        } else {
+            scene_manager::transition(player, Some(save.scene.clone()), Spawn::Position(position));
+        }
+    }
+}
+
+fn find_player(owner: &Node) -> Option<TRef<'_, Node2D>> {
+    let tree = unsafe { owner.get_tree()?.assume_safe() };
+    let players = tree.get_nodes_in_group("player");
+
+    if players.is_empty() {
+        return None;
+    }
+
+    let player = players.get(0).try_to_object::<Node2D>()?;
+    Some(unsafe { player.assume_safe() })
+}
+
+// File of the scene being played, e.g. "res://World.tscn"
+fn current_scene(node: &Node) -> Option<String> {
+    let tree = unsafe { node.get_tree()?.assume_safe() };
+    let scene = tree.current_scene()?;
+
+    Some(unsafe { scene.assume_safe() }.filename().to_string())
+}
+
+fn with_save_game<R>(node: &Node, f: impl FnOnce(&mut SaveGame, TRef<Node>) -> R) -> Option<R> {
+    let save_game = node.get_node(SAVE_GAME_PATH)?;
+    let save_game = unsafe { save_game.assume_safe() };
+
+    save_game.cast_instance::<SaveGame>()?.map_mut(f).ok()
+}
+
+// Writes the save file through the `SaveGame` singleton, false if it could not
+pub fn save(node: &Node) -> bool {
+    let saved = with_save_game(node, |save_game, owner| save_game.save_game(&owner));
+
+    if saved.is_none() {
+        godot_print!("Could not reach SaveGame, the game is not saved");
+    }
+
+    saved.unwrap_or(false)
+}
+
+// Whether `node` was destroyed in a saved game and should not come back
+pub fn is_consumed(node: &Node) -> bool {
+    let id = node_id(node);
+
+    with_save_game(node, |save_game, _| save_game.data.world.is_consumed(&id)).unwrap_or(false)
+}
+
+pub fn mark_consumed(node: &Node) {
+    let id = node_id(node);
+
+    with_save_game(node, |save_game, _| save_game.data.world.consume(&id));
+}
+
+// Makes `node` the checkpoint the player respawns at
+pub fn activate_checkpoint(node: &Node) {
+    let id = node_id(node);
+
+    with_save_game(node, |save_game, _| {
+        save_game.data.world.checkpoint = Some(id)
+    });
+}
+
+pub fn is_active_checkpoint(node: &Node) -> bool {
+    let id = node_id(node);
+
+    with_save_game(node, |save_game, _| {
+        save_game.data.world.checkpoint.as_deref() == Some(id.as_str())
+    })
+    .unwrap_or(false)
+}
+
+// Puts the player back where the save file left it, if there is one and it was
+// not done yet this session
+pub fn restore_player(player: &Node2D, stats: &Node) {
+    with_save_game(player, |save_game, _| {
+        save_game.restore_player(player, stats)
+    });
+}
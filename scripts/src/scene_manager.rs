@@ -0,0 +1,237 @@
+use gdnative::api::*;
+use gdnative::prelude::*;
+
+use crate::utils::get_ref;
+
+const SCENE_MANAGER_PATH: &str = "/root/SceneManager";
+
+// Where the player appears after a transition
+#[derive(Clone, Debug, PartialEq)]
+pub enum Spawn {
+    // Name of a node in the "spawn_points" group
+    Point(String),
+    Position(Vector2),
+}
+
+enum Transition {
+    Idle,
+    // `scene` is `None` for a warp inside the current scene
+    FadingOut {
+        scene: Option<String>,
+        spawn: Spawn,
+        time: f64,
+    },
+    FadingIn {
+        time: f64,
+    },
+}
+
+// SceneManager "class".
+// Autoloaded singleton moving the player between scenes, or between two points
+// of the same scene, behind a fade to black. The tree is paused while the screen
+// darkens. Autoloads such as `PlayerStats` and `Inventory` carry over as they are.
+#[derive(NativeClass)]
+#[inherit(CanvasLayer)]
+#[register_with(Self::register_signals)]
+pub struct SceneManager {
+    #[property(default = 0.3)]
+    fade_duration: f64,
+
+    transition: Transition,
+    // Taken by the player of the next scene
+    pending_spawn: Option<Spawn>,
+    // Found in `_ready`, transitions work without it
+    fade: Option<Ref<CanvasItem>>,
+}
+
+#[gdnative::methods]
+impl SceneManager {
+    // The "constructor" of the class.
+    fn new(_owner: &CanvasLayer) -> Self {
+        SceneManager {
+            fade_duration: 0.3,
+
+            transition: Transition::Idle,
+            pending_spawn: None,
+            fade: None,
+        }
+    }
+
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder.add_signal(Signal {
+            name: "scene_changed",
+            args: &[SignalArgument {
+                name: "scene",
+                default: Variant::from_str(""),
+                export_info: ExportInfo::new(VariantType::GodotString),
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
+    }
+
+    #[export]
+    fn _ready(&mut self, owner: &CanvasLayer) {
+        // Transitions still work, only without the fade
+        match get_ref::<CanvasItem>(owner, "Fade") {
+            Ok(fade) => {
+                self.fade = Some(fade);
+                self.set_fade(0.0);
+            }
+            Err(error) => godot_print!("{}", error),
+        }
+    }
+
+    #[export]
+    fn _process(&mut self, owner: &CanvasLayer, delta: f64) {
+        let progress = match self.advance(delta) {
+            Some(progress) => progress,
+            None => return,
+        };
+
+        if matches!(self.transition, Transition::FadingOut { .. }) {
+            self.set_fade(progress);
+
+            if progress >= 1.0 {
+                self.switch(owner);
+            }
+        } else {
+            self.set_fade(1.0 - progress);
+
+            if progress >= 1.0 {
+                self.transition = Transition::Idle;
+            }
+        }
+    }
+
+    // Fades out, loads `scene` and fades in with the player at `spawn_point`.
+    // An empty `scene` warps inside the current one.
+    #[export]
+    fn change_scene(&mut self, owner: &CanvasLayer, scene: String, spawn_point: String) {
+        let scene = if scene.is_empty() { None } else { Some(scene) };
+        self.start(owner, scene, Spawn::Point(spawn_point));
+    }
+}
+
+impl SceneManager {
+    // Moves the running fade forward, returns how far along it is from 0 to 1
+    fn advance(&mut self, delta: f64) -> Option<f64> {
+        let time = match &mut self.transition {
+            Transition::Idle => return None,
+            Transition::FadingOut { time, .. } | Transition::FadingIn { time } => time,
+        };
+
+        *time += delta;
+        Some((*time / self.fade_duration.max(0.01)).min(1.0))
+    }
+
+    // Does nothing while a transition is running, e.g. when walking through a door twice
+    pub fn start(&mut self, owner: &CanvasLayer, scene: Option<String>, spawn: Spawn) {
+        if !matches!(self.transition, Transition::Idle) {
+            return;
+        }
+
+        set_paused(owner, true);
+        self.transition = Transition::FadingOut {
+            scene,
+            spawn,
+            time: 0.0,
+        };
+    }
+
+    // Called once the screen is black
+    fn switch(&mut self, owner: &CanvasLayer) {
+        let transition =
+            std::mem::replace(&mut self.transition, Transition::FadingIn { time: 0.0 });
+
+        if let Transition::FadingOut { scene, spawn, .. } = transition {
+            let tree = unsafe { owner.get_tree().unwrap().assume_safe() };
+
+            match scene {
+                Some(scene) => {
+                    self.pending_spawn = Some(spawn);
+
+                    if tree.change_scene(scene.as_str()).is_err() {
+                        godot_print!("Could not change to scene {}. Check name.", scene);
+                        self.pending_spawn = None;
+                    } else {
+                        owner.emit_signal("scene_changed", &[scene.to_variant()]);
+                    }
+                }
+                None => {
+                    if let Some(position) = spawn_position(owner, &spawn) {
+                        tree.call_group("player", "set_global_position", &[position.to_variant()]);
+                    }
+                }
+            }
+
+            set_paused(owner, false);
+        }
+    }
+
+    fn set_fade(&self, alpha: f64) {
+        let fade = match &self.fade {
+            Some(fade) => unsafe { fade.assume_safe() },
+            None => return,
+        };
+
+        fade.set_modulate(Color::rgba(1.0, 1.0, 1.0, alpha as f32));
+    }
+}
+
+fn set_paused(owner: &CanvasLayer, paused: bool) {
+    unsafe { owner.get_tree().unwrap().assume_safe().set_pause(paused) };
+}
+
+// Global position of `spawn` in the current scene
+fn spawn_position(node: &Node, spawn: &Spawn) -> Option<Vector2> {
+    let name = match spawn {
+        Spawn::Point(name) => name,
+        Spawn::Position(position) => return Some(*position),
+    };
+
+    let tree = unsafe { node.get_tree()?.assume_safe() };
+    let spawn_points = tree.get_nodes_in_group("spawn_points");
+
+    let position = spawn_points
+        .iter()
+        .filter_map(|point| point.try_to_object::<Node2D>())
+        .map(|point| unsafe { point.assume_safe() })
+        .find(|point| point.name().to_string() == *name)
+        .map(|point| point.global_position());
+
+    if position.is_none() {
+        godot_print!("Could not find spawn point {}. Check name.", name);
+    }
+
+    position
+}
+
+fn with_scene_manager<R>(
+    node: &Node,
+    f: impl FnOnce(&mut SceneManager, TRef<CanvasLayer>) -> R,
+) -> Option<R> {
+    let scene_manager = node.get_node(SCENE_MANAGER_PATH)?;
+    let scene_manager = unsafe { scene_manager.assume_safe() };
+
+    scene_manager
+        .cast::<CanvasLayer>()?
+        .cast_instance::<SceneManager>()?
+        .map_mut(f)
+        .ok()
+}
+
+// Fades to `scene`, or stays in the current one if `None`, with the player at `spawn`
+pub fn transition(node: &Node, scene: Option<String>, spawn: Spawn) {
+    with_scene_manager(node, |scene_manager, owner| {
+        scene_manager.start(&owner, scene, spawn)
+    });
+}
+
+// Where the player of a freshly loaded scene should stand, if a transition says so
+pub fn take_spawn_position(player: &Node) -> Option<Vector2> {
+    let spawn = with_scene_manager(player, |scene_manager, _| {
+        scene_manager.pending_spawn.take()
+    })??;
+
+    spawn_position(player, &spawn)
+}
@@ -14,13 +14,13 @@ impl SoftCollision {
     }
 
     #[export]
-    fn is_colliding(&self, owner: &Area2D) -> bool {
+    pub fn is_colliding(&self, owner: &Area2D) -> bool {
         let area = owner.get_overlapping_areas();
         !area.is_empty()
     }
 
     #[export]
-    fn get_push_vector(&self, owner: &Area2D) -> Vector2 {
+    pub fn get_push_vector(&self, owner: &Area2D) -> Vector2 {
         let areas = owner.get_overlapping_areas();
         let mut push_vector = Vector2::zero();
 
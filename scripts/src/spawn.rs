@@ -0,0 +1,291 @@
+use gdnative::prelude::*;
+use rand::Rng;
+
+// Where a spawner may place enemies, in global coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpawnArea {
+    Rect { center: Vector2, extents: Vector2 },
+    Circle { center: Vector2, radius: f32 },
+}
+
+impl SpawnArea {
+    pub fn random_point<R: Rng + ?Sized>(&self, rng: &mut R) -> Vector2 {
+        match *self {
+            SpawnArea::Rect { center, extents } => {
+                center
+                    + Vector2::new(
+                        rng.gen_range(-extents.x..=extents.x),
+                        rng.gen_range(-extents.y..=extents.y),
+                    )
+            }
+            SpawnArea::Circle { center, radius } => {
+                let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+                // sqrt keeps the points uniform over the disc
+                let distance = radius * rng.gen_range(0.0_f32..=1.0).sqrt();
+
+                center + Vector2::new(angle.cos(), angle.sin()) * distance
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WaveConfig {
+    // Enemies in the first wave
+    pub base_count: u32,
+    // Enemies added by every later wave
+    pub count_growth: u32,
+    // Max health added to enemies per wave after the first, as a fraction (0.25 = +25%)
+    pub health_growth: f64,
+    // Seconds between a cleared wave and the next one
+    pub break_time: f32,
+    // Seconds between two spawns of the same wave
+    pub spawn_interval: f32,
+    // 0 for endless
+    pub max_waves: u32,
+}
+
+impl WaveConfig {
+    pub fn count(&self, wave: u32) -> u32 {
+        self.base_count + self.count_growth * wave.saturating_sub(1)
+    }
+
+    pub fn health_bonus(&self, wave: u32) -> f64 {
+        self.health_growth * wave.saturating_sub(1) as f64
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SpawnEvent {
+    Spawn,
+    WaveStarted(u32),
+    WaveCleared(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Phase {
+    // Keeps `max_alive` enemies around, refilling one slot per `respawn_delay`
+    Continuous { filled: bool },
+    Break,
+    Wave { remaining: u32 },
+    Finished,
+}
+
+// Decides when a spawner spawns, independent of what and where.
+// Every `Spawn` event returned by `tick` counts as alive until `died` is called.
+pub struct SpawnDirector {
+    max_alive: u32,
+    respawn_delay: f32,
+    waves: Option<WaveConfig>,
+
+    phase: Phase,
+    alive: u32,
+    wave: u32,
+    timer: f32,
+}
+
+impl SpawnDirector {
+    // `max_alive` is at least 1, with 0 nothing would ever spawn
+    pub fn continuous(max_alive: u32, respawn_delay: f32) -> Self {
+        SpawnDirector {
+            max_alive: max_alive.max(1),
+            respawn_delay,
+            waves: None,
+
+            phase: Phase::Continuous { filled: false },
+            alive: 0,
+            wave: 0,
+            timer: 0.0,
+        }
+    }
+
+    // The first wave starts on the first tick. `max_alive` is at least 1, with 0
+    // the first wave would never spawn nor end
+    pub fn waves(max_alive: u32, config: WaveConfig) -> Self {
+        SpawnDirector {
+            max_alive: max_alive.max(1),
+            respawn_delay: 0.0,
+            waves: Some(config),
+
+            phase: Phase::Break,
+            alive: 0,
+            wave: 0,
+            timer: 0.0,
+        }
+    }
+
+    // Max health bonus for enemies spawned now, 0 outside wave mode
+    pub fn wave_health_bonus(&self) -> f64 {
+        match self.waves {
+            Some(config) => config.health_bonus(self.wave),
+            None => 0.0,
+        }
+    }
+
+    pub fn died(&mut self) {
+        self.alive = self.alive.saturating_sub(1);
+    }
+
+    pub fn tick(&mut self, delta: f32) -> Vec<SpawnEvent> {
+        let mut events = Vec::new();
+        self.timer -= delta;
+
+        match self.phase {
+            Phase::Continuous { filled: false } => {
+                while self.alive < self.max_alive {
+                    self.spawn(&mut events);
+                }
+
+                self.phase = Phase::Continuous { filled: true };
+                self.timer = self.respawn_delay;
+            }
+            Phase::Continuous { filled: true } => {
+                if self.alive >= self.max_alive {
+                    // The delay only starts once a slot frees up
+                    self.timer = self.respawn_delay;
+                } else if self.timer <= 0.0 {
+                    self.spawn(&mut events);
+                    self.timer = self.respawn_delay;
+                }
+            }
+            Phase::Break => {
+                let config = self.waves.expect("Break only happens in wave mode");
+
+                if self.timer <= 0.0 {
+                    if config.max_waves != 0 && self.wave >= config.max_waves {
+                        self.phase = Phase::Finished;
+                    } else {
+                        self.wave += 1;
+                        self.phase = Phase::Wave {
+                            remaining: config.count(self.wave),
+                        };
+                        self.timer = 0.0;
+
+                        events.push(SpawnEvent::WaveStarted(self.wave));
+                    }
+                }
+            }
+            Phase::Wave { remaining } => {
+                let config = self.waves.expect("Wave only happens in wave mode");
+
+                if remaining > 0 {
+                    if self.timer <= 0.0 && self.alive < self.max_alive {
+                        self.spawn(&mut events);
+                        self.phase = Phase::Wave {
+                            remaining: remaining - 1,
+                        };
+                        self.timer = config.spawn_interval;
+                    }
+                } else if self.alive == 0 {
+                    events.push(SpawnEvent::WaveCleared(self.wave));
+
+                    self.phase = Phase::Break;
+                    self.timer = config.break_time;
+                }
+            }
+            Phase::Finished => {}
+        }
+
+        events
+    }
+
+    fn spawn(&mut self, events: &mut Vec<SpawnEvent>) {
+        self.alive += 1;
+        events.push(SpawnEvent::Spawn);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawns(events: &[SpawnEvent]) -> usize {
+        events.iter().filter(|e| **e == SpawnEvent::Spawn).count()
+    }
+
+    fn config() -> WaveConfig {
+        WaveConfig {
+            base_count: 2,
+            count_growth: 1,
+            health_growth: 0.5,
+            break_time: 2.0,
+            spawn_interval: 0.5,
+            max_waves: 2,
+        }
+    }
+
+    #[test]
+    fn continuous_fills_up_then_respawns_after_delay() {
+        let mut director = SpawnDirector::continuous(3, 1.0);
+        assert_eq!(spawns(&director.tick(0.1)), 3);
+        assert_eq!(spawns(&director.tick(5.0)), 0);
+
+        director.died();
+        // The delay starts when the slot frees up, not when the last one spawned
+        assert_eq!(spawns(&director.tick(0.5)), 0);
+        assert_eq!(spawns(&director.tick(0.5)), 1);
+        assert_eq!(spawns(&director.tick(5.0)), 0);
+    }
+
+    #[test]
+    fn waves_grow_and_advance_once_cleared() {
+        let mut director = SpawnDirector::waves(10, config());
+
+        assert_eq!(director.tick(0.1), vec![SpawnEvent::WaveStarted(1)]);
+        assert_eq!(director.wave_health_bonus(), 0.0);
+        assert_eq!(director.tick(0.1), vec![SpawnEvent::Spawn]);
+        assert_eq!(spawns(&director.tick(0.25)), 0);
+        assert_eq!(spawns(&director.tick(0.25)), 1);
+        assert_eq!(director.tick(1.0), vec![]);
+
+        director.died();
+        director.died();
+        assert_eq!(director.tick(0.1), vec![SpawnEvent::WaveCleared(1)]);
+        assert_eq!(director.tick(1.0), vec![]);
+        assert_eq!(director.tick(1.0), vec![SpawnEvent::WaveStarted(2)]);
+        assert_eq!(director.wave_health_bonus(), 0.5);
+
+        let spawned: usize = (0..10).map(|_| spawns(&director.tick(0.5))).sum();
+        assert_eq!(spawned, 3);
+    }
+
+    #[test]
+    fn waves_finish_after_max_waves() {
+        let mut director = SpawnDirector::waves(10, config());
+
+        let spawned: Vec<usize> = (0..2)
+            .map(|_| {
+                let spawned = (0..10).map(|_| spawns(&director.tick(2.0))).sum();
+                (0..spawned).for_each(|_| director.died());
+                director.tick(0.1);
+                spawned
+            })
+            .collect();
+        assert_eq!(spawned, vec![2, 3]);
+
+        let events: Vec<_> = (0..10).flat_map(|_| director.tick(2.0)).collect();
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn waves_wait_for_a_free_slot() {
+        let mut director = SpawnDirector::waves(1, config());
+        director.tick(0.1);
+
+        assert_eq!(spawns(&director.tick(1.0)), 1);
+        assert_eq!(spawns(&director.tick(1.0)), 0);
+
+        director.died();
+        assert_eq!(spawns(&director.tick(1.0)), 1);
+    }
+
+    #[test]
+    fn zero_max_alive_still_spawns() {
+        let mut director = SpawnDirector::waves(0, config());
+        director.tick(0.1);
+        assert_eq!(spawns(&director.tick(0.1)), 1);
+
+        let mut director = SpawnDirector::continuous(0, 1.0);
+        assert_eq!(spawns(&director.tick(0.1)), 1);
+    }
+}
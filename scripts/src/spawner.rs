@@ -0,0 +1,262 @@
+use gdnative::api::*;
+use gdnative::prelude::*;
+use rand::Rng;
+
+use crate::rng_service::with_rng;
+use crate::spawn::*;
+use crate::utils::load_scene;
+use crate::weighted_table::WeightedTable;
+
+// Spawner "class".
+// Instances enemies from a weighted table of scenes next to itself, so it should
+// sit in the same `YSort` as the enemies. Spawn areas are its `CollisionShape2D`
+// children with a rectangle or circle shape; without any, `spawn_radius` around it.
+#[derive(NativeClass)]
+#[inherit(Node2D)]
+#[register_with(Self::register_signals)]
+pub struct Spawner {
+    // Scene path -> weight, e.g. { "res://Enemies/Bat.tscn": 1 }
+    #[property]
+    spawn_table: Dictionary,
+    #[property(default = 3)]
+    max_alive: i64,
+    #[property(default = 5.0)]
+    respawn_delay: f32,
+    #[property(default = 32.0)]
+    spawn_radius: f32,
+    #[property(path = "wave/enabled", default = false)]
+    wave_mode: bool,
+    #[property(path = "wave/base_count", default = 3)]
+    wave_base_count: i64,
+    #[property(path = "wave/count_growth", default = 2)]
+    wave_count_growth: i64,
+    #[property(path = "wave/health_growth", default = 0.25)]
+    wave_health_growth: f64,
+    #[property(path = "wave/break_time", default = 5.0)]
+    wave_break_time: f32,
+    #[property(path = "wave/spawn_interval", default = 0.5)]
+    wave_spawn_interval: f32,
+    #[property(path = "wave/max_waves", default = 0)]
+    wave_max_waves: i64,
+
+    director: SpawnDirector,
+    table: WeightedTable<Ref<PackedScene>>,
+    areas: Vec<SpawnArea>,
+}
+
+#[gdnative::methods]
+impl Spawner {
+    // The "constructor" of the class.
+    fn new(_owner: &Node2D) -> Self {
+        Spawner {
+            spawn_table: Dictionary::new_shared(),
+            max_alive: 3,
+            respawn_delay: 5.0,
+            spawn_radius: 32.0,
+            wave_mode: false,
+            wave_base_count: 3,
+            wave_count_growth: 2,
+            wave_health_growth: 0.25,
+            wave_break_time: 5.0,
+            wave_spawn_interval: 0.5,
+            wave_max_waves: 0,
+
+            director: SpawnDirector::continuous(0, 0.0),
+            table: WeightedTable::default(),
+            areas: Vec::new(),
+        }
+    }
+
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder.add_signal(Signal {
+            name: "wave_started",
+            args: &[SignalArgument {
+                name: "wave",
+                default: Variant::from_i64(1),
+                export_info: ExportInfo::new(VariantType::I64),
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
+
+        builder.add_signal(Signal {
+            name: "wave_cleared",
+            args: &[SignalArgument {
+                name: "wave",
+                default: Variant::from_i64(1),
+                export_info: ExportInfo::new(VariantType::I64),
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
+    }
+
+    #[export]
+    fn _ready(&mut self, owner: &Node2D) {
+        for (path, weight) in self.spawn_table.iter() {
+            let path = path.to_godot_string().to_string();
+
+            match load_scene(&path) {
+                Some(scene) => self.table.add(scene, weight.to_i64().max(0) as u32),
+                None => godot_print!("Could not load spawn scene {}. Check name.", path),
+            }
+        }
+
+        self.areas = spawn_areas(owner);
+        if self.areas.is_empty() {
+            self.areas.push(SpawnArea::Circle {
+                center: owner.global_position(),
+                radius: self.spawn_radius,
+            });
+        }
+
+        let max_alive = self.max_alive.max(0) as u32;
+        self.director = if self.wave_mode {
+            SpawnDirector::waves(
+                max_alive,
+                WaveConfig {
+                    base_count: self.wave_base_count.max(0) as u32,
+                    count_growth: self.wave_count_growth.max(0) as u32,
+                    health_growth: self.wave_health_growth,
+                    break_time: self.wave_break_time,
+                    spawn_interval: self.wave_spawn_interval,
+                    max_waves: self.wave_max_waves.max(0) as u32,
+                },
+            )
+        } else {
+            SpawnDirector::continuous(max_alive, self.respawn_delay)
+        };
+    }
+
+    #[export]
+    fn _process(&mut self, owner: TRef<Node2D>, delta: f64) {
+        for event in self.director.tick(delta as f32) {
+            match event {
+                SpawnEvent::Spawn => self.spawn(owner),
+                SpawnEvent::WaveStarted(wave) => {
+                    owner.emit_signal("wave_started", &[(wave as i64).to_variant()]);
+                }
+                SpawnEvent::WaveCleared(wave) => {
+                    owner.emit_signal("wave_cleared", &[(wave as i64).to_variant()]);
+                }
+            }
+        }
+    }
+
+    // Accepting signal from spawned enemies
+    #[export]
+    fn _on_enemy_tree_exited(&mut self, _owner: &Node2D) {
+        self.director.died();
+    }
+}
+
+impl Spawner {
+    fn spawn(&mut self, owner: TRef<Node2D>) {
+        let table = &self.table;
+        let areas = &self.areas;
+
+        let (scene, position) = with_rng(&owner, "spawner", |rng| {
+            let area = areas[rng.gen_range(0..areas.len())];
+            (table.pick(rng).cloned(), area.random_point(rng))
+        });
+
+        let scene = match scene {
+            Some(scene) => scene,
+            None => {
+                // Nothing to spawn, give the slot back
+                self.director.died();
+                return;
+            }
+        };
+
+        let enemy = unsafe { scene.assume_safe() }
+            .instance(PackedScene::GEN_EDIT_STATE_DISABLED)
+            .expect("should be able to instance scene");
+        let enemy = unsafe { enemy.assume_safe() };
+
+        // Spawned enemies are not part of the saved world
+        enemy.set("persistent", false);
+
+        let parent = owner.get_parent().expect("Spawner should have a parent");
+        let parent = unsafe { parent.assume_safe() };
+
+        // Placed before entering the tree, so `_ready` already sees the final position
+        if let Some(enemy) = enemy.cast::<Node2D>() {
+            let position = match parent.cast::<Node2D>() {
+                Some(parent) => parent.to_local(position),
+                None => position,
+            };
+            enemy.set_position(position);
+        }
+
+        enemy
+            .connect(
+                "tree_exited",
+                owner,
+                "_on_enemy_tree_exited",
+                VariantArray::new_shared(),
+                1,
+            )
+            .unwrap();
+
+        parent.add_child(enemy, false);
+
+        self.apply_wave_bonus(enemy);
+    }
+
+    // Later waves get tougher through a max health modifier on the enemy `Stats`
+    fn apply_wave_bonus(&self, enemy: TRef<Node>) {
+        let bonus = self.director.wave_health_bonus();
+        if bonus <= 0.0 {
+            return;
+        }
+
+        let stats = match enemy.get_node("Stats") {
+            Some(stats) => unsafe { stats.assume_safe() },
+            None => return,
+        };
+
+        unsafe {
+            stats.call(
+                "add_stat_modifier",
+                &[
+                    "max_health".to_variant(),
+                    1.to_variant(),
+                    bonus.to_variant(),
+                    "wave".to_variant(),
+                    0.0.to_variant(),
+                ],
+            );
+            stats.call("set_health", &[stats.get("max_health")]);
+        }
+    }
+}
+
+// Rectangle and circle `CollisionShape2D` children of `owner`
+fn spawn_areas(owner: &Node2D) -> Vec<SpawnArea> {
+    let mut areas = Vec::new();
+
+    for child in owner.get_children().iter() {
+        let shape_node = match child.try_to_object::<CollisionShape2D>() {
+            Some(shape_node) => unsafe { shape_node.assume_safe() },
+            None => continue,
+        };
+        let shape = match shape_node.shape() {
+            Some(shape) => unsafe { shape.assume_safe() },
+            None => continue,
+        };
+        let center = shape_node.global_position();
+
+        if let Some(rectangle) = shape.cast::<RectangleShape2D>() {
+            areas.push(SpawnArea::Rect {
+                center,
+                extents: rectangle.extents(),
+            });
+        } else if let Some(circle) = shape.cast::<CircleShape2D>() {
+            areas.push(SpawnArea::Circle {
+                center,
+                radius: circle.radius() as f32,
+            });
+        }
+    }
+
+    areas
+}
@@ -0,0 +1,188 @@
+// Generic table-driven state machine.
+// `S` is the state, `E` the event that may trigger a transition and `C` the context
+// that guards read and enter/exit hooks update.
+pub type Guard<C> = fn(&C) -> bool;
+pub type Hook<C> = fn(&mut C);
+
+struct Transition<S, E, C> {
+    // `None` matches every state
+    from: Option<S>,
+    event: E,
+    to: S,
+    guard: Option<Guard<C>>,
+}
+
+pub struct StateMachine<S, E, C> {
+    state: S,
+    transitions: Vec<Transition<S, E, C>>,
+    enter_hooks: Vec<(S, Hook<C>)>,
+    exit_hooks: Vec<(S, Hook<C>)>,
+}
+
+impl<S, E, C> StateMachine<S, E, C>
+where
+    S: Copy + PartialEq,
+    E: Copy + PartialEq,
+{
+    pub fn new(initial: S) -> Self {
+        StateMachine {
+            state: initial,
+            transitions: Vec::new(),
+            enter_hooks: Vec::new(),
+            exit_hooks: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> S {
+        self.state
+    }
+
+    pub fn add_transition(&mut self, from: S, event: E, to: S, guard: Option<Guard<C>>) {
+        self.transitions.push(Transition {
+            from: Some(from),
+            event,
+            to,
+            guard,
+        });
+    }
+
+    // Transition allowed from every state except `to` itself.
+    pub fn add_any_transition(&mut self, event: E, to: S, guard: Option<Guard<C>>) {
+        self.transitions.push(Transition {
+            from: None,
+            event,
+            to,
+            guard,
+        });
+    }
+
+    pub fn add_enter_hook(&mut self, state: S, hook: Hook<C>) {
+        self.enter_hooks.push((state, hook));
+    }
+
+    pub fn add_exit_hook(&mut self, state: S, hook: Hook<C>) {
+        self.exit_hooks.push((state, hook));
+    }
+
+    // State the machine would move to on `event`, without changing anything.
+    // Transitions are checked in the order they were added; the first passing one wins.
+    pub fn next_state(&self, event: E, context: &C) -> Option<S> {
+        self.transitions
+            .iter()
+            .filter(|transition| transition.event == event)
+            .filter(|transition| match transition.from {
+                Some(from) => from == self.state,
+                None => transition.to != self.state,
+            })
+            .find(|transition| match transition.guard {
+                Some(guard) => guard(context),
+                None => true,
+            })
+            .map(|transition| transition.to)
+    }
+
+    // Applies `event`, running exit hooks of the old state and enter hooks of the new one.
+    // Returns the new state if a transition happened.
+    pub fn fire(&mut self, event: E, context: &mut C) -> Option<S> {
+        let next = self.next_state(event, context)?;
+        self.change_state(next, context);
+
+        Some(next)
+    }
+
+    fn change_state(&mut self, next: S, context: &mut C) {
+        let previous = self.state;
+
+        for (_, hook) in self.exit_hooks.iter().filter(|(s, _)| *s == previous) {
+            hook(context);
+        }
+
+        self.state = next;
+
+        for (_, hook) in self.enter_hooks.iter().filter(|(s, _)| *s == next) {
+            hook(context);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Light {
+        Off,
+        On,
+        Broken,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Switch {
+        Toggle,
+        Break,
+    }
+
+    #[derive(Default)]
+    struct Context {
+        powered: bool,
+        log: Vec<&'static str>,
+    }
+
+    fn machine() -> StateMachine<Light, Switch, Context> {
+        let mut machine = StateMachine::<_, _, Context>::new(Light::Off);
+
+        machine.add_transition(Light::Off, Switch::Toggle, Light::On, Some(|c| c.powered));
+        machine.add_transition(Light::On, Switch::Toggle, Light::Off, None);
+        machine.add_any_transition(Switch::Break, Light::Broken, None);
+
+        machine.add_exit_hook(Light::Off, |c| c.log.push("exit off"));
+        machine.add_enter_hook(Light::On, |c| c.log.push("enter on"));
+        machine.add_exit_hook(Light::On, |c| c.log.push("exit on"));
+        machine.add_enter_hook(Light::Broken, |c| c.log.push("enter broken"));
+
+        machine
+    }
+
+    #[test]
+    fn guard_rejects_transition() {
+        let mut machine = machine();
+        let mut context = Context::default();
+
+        assert_eq!(machine.fire(Switch::Toggle, &mut context), None);
+        assert_eq!(machine.state(), Light::Off);
+        assert!(context.log.is_empty());
+
+        context.powered = true;
+        assert_eq!(machine.fire(Switch::Toggle, &mut context), Some(Light::On));
+    }
+
+    #[test]
+    fn exit_hooks_run_before_enter_hooks() {
+        let mut machine = machine();
+        let mut context = Context {
+            powered: true,
+            ..Context::default()
+        };
+
+        machine.fire(Switch::Toggle, &mut context);
+        machine.fire(Switch::Break, &mut context);
+
+        assert_eq!(
+            context.log,
+            vec!["exit off", "enter on", "exit on", "enter broken"]
+        );
+    }
+
+    #[test]
+    fn any_transition_does_not_reenter_its_state() {
+        let mut machine = machine();
+        let mut context = Context::default();
+
+        assert_eq!(
+            machine.fire(Switch::Break, &mut context),
+            Some(Light::Broken)
+        );
+        assert_eq!(machine.fire(Switch::Break, &mut context), None);
+        assert_eq!(context.log, vec!["exit off", "enter broken"]);
+    }
+}
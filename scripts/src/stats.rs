@@ -1,4 +1,8 @@
-// use crate::hitbox::*;
+use crate::damage::*;
+use crate::modifiers::*;
+use crate::progression::*;
+use crate::status::*;
+use crate::vitals::*;
 use gdnative::api::*;
 use gdnative::prelude::*;
 
@@ -11,6 +15,55 @@ pub struct Stats {
     max_health: i64,
     #[property(default = 1)]
     health: i64,
+    #[property(default = 100.0)]
+    max_stamina: f64,
+    #[property(default = 100.0)]
+    stamina: f64,
+    // Stamina regained per second
+    #[property(default = 25.0)]
+    stamina_regen: f64,
+    #[property(path = "resistance/physical", default = 0.0)]
+    physical_resistance: f64,
+    #[property(path = "resistance/fire", default = 0.0)]
+    fire_resistance: f64,
+    #[property(path = "resistance/poison", default = 0.0)]
+    poison_resistance: f64,
+    #[property(path = "immunity/poison", default = false)]
+    poison_immune: bool,
+    #[property(path = "immunity/burn", default = false)]
+    burn_immune: bool,
+    #[property(path = "immunity/slow", default = false)]
+    slow_immune: bool,
+    #[property(path = "immunity/stun", default = false)]
+    stun_immune: bool,
+    #[property(path = "base/attack", default = 0.0)]
+    attack: f64,
+    #[property(path = "base/defense", default = 0.0)]
+    defense: f64,
+    // Multiplier applied to the owner's `max_speed`
+    #[property(path = "base/move_speed", default = 1.0)]
+    move_speed: f64,
+    #[property(path = "base/crit_chance", default = 0.0)]
+    crit_chance: f64,
+    // XP given to the player when this one dies
+    #[property(path = "xp/reward", default = 0)]
+    xp_reward: i64,
+    // XP from level n to n + 1 is `base * n^exponent`
+    #[property(path = "xp/base", default = 10.0)]
+    xp_base: f64,
+    #[property(path = "xp/exponent", default = 1.5)]
+    xp_exponent: f64,
+    #[property(path = "xp/max_level", default = 20)]
+    max_level: i64,
+    // Stat name -> flat gain per level, e.g. `{"max_health": 1}`
+    #[property]
+    growth: Dictionary,
+
+    // `max_health` before modifiers
+    base_max_health: i64,
+    statuses: StatusEffects,
+    modifiers: ModifierStack,
+    progression: Progression,
 }
 
 #[gdnative::methods]
@@ -19,6 +72,34 @@ impl Stats {
         Stats {
             max_health: 1,
             health: 1,
+            max_stamina: 100.0,
+            stamina: 100.0,
+            stamina_regen: 25.0,
+            physical_resistance: 0.0,
+            fire_resistance: 0.0,
+            poison_resistance: 0.0,
+            poison_immune: false,
+            burn_immune: false,
+            slow_immune: false,
+            stun_immune: false,
+            attack: 0.0,
+            defense: 0.0,
+            move_speed: 1.0,
+            crit_chance: 0.0,
+            xp_reward: 0,
+            xp_base: 10.0,
+            xp_exponent: 1.5,
+            max_level: 20,
+            growth: Dictionary::new_shared(),
+
+            base_max_health: 1,
+            statuses: StatusEffects::default(),
+            modifiers: ModifierStack::default(),
+            progression: Progression::new(XpCurve {
+                base: 10.0,
+                exponent: 1.5,
+                max_level: 20,
+            }),
         }
     }
 
@@ -47,25 +128,274 @@ impl Stats {
                 usage: PropertyUsage::DEFAULT,
             }],
         });
+
+        builder.add_signal(Signal {
+            name: "stamina_changed",
+            args: &[SignalArgument {
+                name: "value",
+                default: Variant::from_f64(100.0),
+                export_info: ExportInfo::new(VariantType::F64),
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
+
+        builder.add_signal(Signal {
+            name: "max_stamina_changed",
+            args: &[SignalArgument {
+                name: "value",
+                default: Variant::from_f64(100.0),
+                export_info: ExportInfo::new(VariantType::F64),
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
+
+        builder.add_signal(Signal {
+            name: "stat_changed",
+            args: &[
+                SignalArgument {
+                    name: "name",
+                    default: Variant::from_str("attack"),
+                    export_info: ExportInfo::new(VariantType::GodotString),
+                    usage: PropertyUsage::DEFAULT,
+                },
+                SignalArgument {
+                    name: "value",
+                    default: Variant::from_f64(0.0),
+                    export_info: ExportInfo::new(VariantType::F64),
+                    usage: PropertyUsage::DEFAULT,
+                },
+            ],
+        });
+
+        builder.add_signal(Signal {
+            name: "xp_changed",
+            args: &[
+                SignalArgument {
+                    name: "xp",
+                    default: Variant::from_i64(0),
+                    export_info: ExportInfo::new(VariantType::I64),
+                    usage: PropertyUsage::DEFAULT,
+                },
+                SignalArgument {
+                    name: "xp_to_next",
+                    default: Variant::from_i64(0),
+                    export_info: ExportInfo::new(VariantType::I64),
+                    usage: PropertyUsage::DEFAULT,
+                },
+            ],
+        });
+
+        builder.add_signal(Signal {
+            name: "leveled_up",
+            args: &[SignalArgument {
+                name: "level",
+                default: Variant::from_i64(1),
+                export_info: ExportInfo::new(VariantType::I64),
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
+
+        builder.add_signal(Signal {
+            name: "status_applied",
+            args: &[SignalArgument {
+                name: "status",
+                default: Variant::from_str("poison"),
+                export_info: ExportInfo::new(VariantType::GodotString),
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
+
+        builder.add_signal(Signal {
+            name: "status_expired",
+            args: &[SignalArgument {
+                name: "status",
+                default: Variant::from_str("poison"),
+                export_info: ExportInfo::new(VariantType::GodotString),
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
+    }
+
+    #[export]
+    fn _ready(&mut self, _owner: &Node) {
+        self.base_max_health = self.max_health;
+
+        self.progression = Progression::new(XpCurve {
+            base: self.xp_base,
+            exponent: self.xp_exponent,
+            max_level: self.max_level.max(1) as u32,
+        });
+
+        self.statuses
+            .set_immune(StatusKind::Poison, self.poison_immune);
+        self.statuses.set_immune(StatusKind::Burn, self.burn_immune);
+        self.statuses.set_immune(StatusKind::Slow, self.slow_immune);
+        self.statuses.set_immune(StatusKind::Stun, self.stun_immune);
+    }
+
+    #[export]
+    fn _process(&mut self, owner: &Node, delta: f64) {
+        if self.stamina < self.max_stamina {
+            let stamina = regen_stamina(self.stamina, self.max_stamina, self.stamina_regen, delta);
+            self.set_stamina(owner, stamina);
+        }
+
+        self.tick_statuses(owner, delta as f32);
+
+        let expired = self.modifiers.tick(delta as f32);
+        self.stats_changed(owner, &expired);
     }
 
     #[export]
-    fn set_health(&mut self, owner: &Node, value: i64) {
-        self.health = num::clamp(value, 0, self.max_health);
+    pub fn set_health(&mut self, owner: &Node, value: i64) {
+        let (health, ran_out) = change_health(self.health, self.max_health, value);
+        self.health = health;
 
         owner.emit_signal("health_changed", &[self.health.to_variant()]);
 
-        if self.health <= 0 {
+        if ran_out {
+            self.statuses.clear();
             owner.emit_signal("no_health", &[]);
         }
     }
 
     #[export]
-    fn set_max_health(&mut self, owner: &Node, value: i64) {
-        self.max_health = value.max(1);
+    pub fn set_max_health(&mut self, owner: &Node, value: i64) {
+        self.base_max_health = value.max(1);
+
+        self.stats_changed(owner, &[StatKind::MaxHealth]);
+    }
+
+    #[export]
+    pub fn set_stamina(&mut self, owner: &Node, value: f64) {
+        self.stamina = num::clamp(value, 0.0, self.max_stamina);
+
+        owner.emit_signal("stamina_changed", &[self.stamina.to_variant()]);
+    }
+
+    #[export]
+    fn set_max_stamina(&mut self, owner: &Node, value: f64) {
+        self.max_stamina = value.max(0.0);
+
+        self.set_stamina(owner, self.stamina.min(self.max_stamina));
+        owner.emit_signal("max_stamina_changed", &[self.max_stamina.to_variant()]);
+    }
+
+    // Spends `amount` stamina if there is enough of it, returns whether it was spent
+    #[export]
+    pub fn consume_stamina(&mut self, owner: &Node, amount: f64) -> bool {
+        match spend_stamina(self.stamina, amount) {
+            Some(stamina) => {
+                self.set_stamina(owner, stamina);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Effective speed is `max_speed * speed_multiplier`
+    #[export]
+    pub fn get_speed_multiplier(&self, _owner: &Node) -> f32 {
+        let move_speed = self.stat_value(StatKind::MoveSpeed).max(0.0) as f32;
+
+        self.statuses.speed_multiplier() * move_speed
+    }
+
+    // `op`: 0 = flat, 1 = percent-add, 2 = percent-multiply. `duration` <= 0 is permanent.
+    #[export]
+    fn add_stat_modifier(
+        &mut self,
+        owner: &Node,
+        stat: String,
+        op: i64,
+        value: f64,
+        source: String,
+        duration: f64,
+    ) {
+        let stat = match StatKind::from_name(&stat) {
+            Some(stat) => stat,
+            None => {
+                godot_print!("Unknown stat {}", stat);
+                return;
+            }
+        };
+
+        self.add_modifier(
+            owner,
+            StatModifier {
+                stat,
+                op: ModifierOp::from_i64(op),
+                value,
+                source,
+                duration: if duration > 0.0 {
+                    Some(duration as f32)
+                } else {
+                    None
+                },
+            },
+        );
+    }
+
+    #[export]
+    fn remove_stat_modifiers(&mut self, owner: &Node, source: String) {
+        self.remove_modifiers(owner, &source);
+    }
+
+    #[export]
+    fn get_stat(&self, _owner: &Node, stat: String) -> f64 {
+        StatKind::from_name(&stat).map_or(0.0, |stat| self.stat_value(stat))
+    }
+
+    #[export]
+    pub fn add_xp(&mut self, owner: &Node, amount: i64) {
+        if amount <= 0 {
+            return;
+        }
+
+        let gained = self.progression.add_xp(amount as u32);
+
+        if gained > 0 {
+            self.apply_growth(owner);
+
+            // Levelling up restores health
+            self.set_health(owner, self.max_health);
+            owner.emit_signal(
+                "leveled_up",
+                &[(self.progression.level() as i64).to_variant()],
+            );
+        }
+
+        self.xp_changed(owner);
+    }
+
+    // Sets level and XP without the level-up heal, e.g. from a save file
+    #[export]
+    pub fn set_progress(&mut self, owner: &Node, level: i64, xp: i64) {
+        self.progression
+            .restore(level.max(1) as u32, xp.max(0) as u32);
+
+        self.apply_growth(owner);
+        self.xp_changed(owner);
+    }
+
+    #[export]
+    fn get_level(&self, _owner: &Node) -> i64 {
+        self.progression.level() as i64
+    }
 
-        self.set_health(owner, self.health.min(self.max_health));
-        owner.emit_signal("max_health_changed", &[self.health.to_variant()]);
+    #[export]
+    fn get_xp(&self, _owner: &Node) -> i64 {
+        self.progression.xp() as i64
+    }
+
+    #[export]
+    fn get_xp_to_next(&self, _owner: &Node) -> i64 {
+        self.progression.xp_to_next() as i64
+    }
+
+    #[export]
+    pub fn is_stunned(&self, _owner: &Node) -> bool {
+        self.statuses.has(StatusKind::Stun)
     }
 
     // #[export]
@@ -73,3 +403,159 @@ impl Stats {
     //     self.health
     // }
 }
+
+impl Stats {
+    pub fn health(&self) -> i64 {
+        self.health
+    }
+
+    pub fn max_health(&self) -> i64 {
+        self.max_health
+    }
+
+    pub fn max_stamina(&self) -> f64 {
+        self.max_stamina
+    }
+
+    pub fn base_max_health(&self) -> i64 {
+        self.base_max_health
+    }
+
+    pub fn level(&self) -> u32 {
+        self.progression.level()
+    }
+
+    pub fn xp(&self) -> u32 {
+        self.progression.xp()
+    }
+
+    // Given to the player by the enemy owning these stats when it dies
+    pub fn xp_reward(&self) -> i64 {
+        self.xp_reward
+    }
+
+    pub fn resistances(&self) -> Resistances {
+        Resistances {
+            physical: self.physical_resistance,
+            fire: self.fire_resistance,
+            poison: self.poison_resistance,
+            defense: self.stat_value(StatKind::Defense),
+        }
+    }
+
+    pub fn stat_value(&self, stat: StatKind) -> f64 {
+        let base = match stat {
+            StatKind::Attack => self.attack,
+            StatKind::Defense => self.defense,
+            StatKind::MoveSpeed => self.move_speed,
+            StatKind::MaxHealth => self.base_max_health as f64,
+            StatKind::CritChance => self.crit_chance,
+        };
+
+        self.modifiers.value(stat, base)
+    }
+
+    pub fn add_modifier(&mut self, owner: &Node, modifier: StatModifier) {
+        let stat = modifier.stat;
+        self.modifiers.add(modifier);
+
+        self.stats_changed(owner, &[stat]);
+    }
+
+    pub fn remove_modifiers(&mut self, owner: &Node, source: &str) {
+        let changed = self.modifiers.remove_source(source);
+
+        self.stats_changed(owner, &changed);
+    }
+
+    fn stats_changed(&mut self, owner: &Node, stats: &[StatKind]) {
+        for stat in stats.iter().copied() {
+            let value = self.stat_value(stat);
+
+            if stat == StatKind::MaxHealth {
+                self.max_health = (value.round() as i64).max(1);
+
+                if self.health > self.max_health {
+                    self.set_health(owner, self.max_health);
+                }
+                owner.emit_signal("max_health_changed", &[self.max_health.to_variant()]);
+            }
+
+            owner.emit_signal(
+                "stat_changed",
+                &[stat.name().to_variant(), value.to_variant()],
+            );
+        }
+    }
+
+    // Replaces the `level` modifiers with the growth of every level past the first
+    fn apply_growth(&mut self, owner: &Node) {
+        let mut growth = Vec::new();
+
+        for (name, gain) in self.growth.iter() {
+            let name = name.to_godot_string().to_string();
+
+            match StatKind::from_name(&name) {
+                Some(stat) => growth.push((stat, gain.to_f64())),
+                None => godot_print!("Unknown stat {} in growth", name),
+            }
+        }
+
+        self.modifiers.remove_source("level");
+        for modifier in growth_modifiers(&growth, self.progression.level()) {
+            self.modifiers.add(modifier);
+        }
+
+        self.stats_changed(owner, &StatKind::ALL);
+    }
+
+    fn xp_changed(&self, owner: &Node) {
+        owner.emit_signal(
+            "xp_changed",
+            &[
+                (self.progression.xp() as i64).to_variant(),
+                (self.progression.xp_to_next() as i64).to_variant(),
+            ],
+        );
+    }
+
+    // Applies `damage` after resistances and returns it with the amount actually taken
+    pub fn take_damage(&mut self, owner: &Node, damage: DamageInfo) -> DamageInfo {
+        let amount = resolve_damage(&damage, &self.resistances());
+        self.set_health(owner, self.health - amount);
+
+        if let Some(status) = damage.status {
+            if self.health > 0 && self.statuses.apply(status) {
+                owner.emit_signal("status_applied", &[status.kind.name().to_variant()]);
+            }
+        }
+
+        DamageInfo { amount, ..damage }
+    }
+
+    fn tick_statuses(&mut self, owner: &Node, delta: f32) {
+        let tick = self.statuses.tick(delta);
+
+        for (kind, amount) in tick.damage {
+            if self.health <= 0 {
+                break;
+            }
+
+            let damage = DamageInfo {
+                amount,
+                damage_type: kind.damage_type().unwrap_or(DamageType::Physical),
+                source: None,
+                knockback: Vector2::zero(),
+                critical: false,
+                status: None,
+            };
+
+            let amount = resolve_damage(&damage, &self.resistances());
+            self.set_health(owner, self.health - amount);
+        }
+
+        for kind in tick.expired {
+            owner.emit_signal("status_expired", &[kind.name().to_variant()]);
+        }
+    }
+}
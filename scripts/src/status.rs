@@ -0,0 +1,299 @@
+use crate::damage::DamageType;
+
+const MAX_POISON_STACKS: u32 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatusKind {
+    Poison,
+    Burn,
+    Slow,
+    Stun,
+}
+
+impl StatusKind {
+    // Matches the `status/kind` property exported by `Hitbox`; anything else means no status
+    pub fn from_i64(value: i64) -> Option<Self> {
+        match value {
+            0 => Some(StatusKind::Poison),
+            1 => Some(StatusKind::Burn),
+            2 => Some(StatusKind::Slow),
+            3 => Some(StatusKind::Stun),
+            _ => None,
+        }
+    }
+
+    pub fn to_i64(self) -> i64 {
+        match self {
+            StatusKind::Poison => 0,
+            StatusKind::Burn => 1,
+            StatusKind::Slow => 2,
+            StatusKind::Stun => 3,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StatusKind::Poison => "poison",
+            StatusKind::Burn => "burn",
+            StatusKind::Slow => "slow",
+            StatusKind::Stun => "stun",
+        }
+    }
+
+    // Type of the tick damage, if the status deals any
+    pub fn damage_type(self) -> Option<DamageType> {
+        match self {
+            StatusKind::Poison => Some(DamageType::Poison),
+            StatusKind::Burn => Some(DamageType::Fire),
+            StatusKind::Slow | StatusKind::Stun => None,
+        }
+    }
+}
+
+// A status as carried by a hit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub duration: f32,
+    // Damage dealt every `tick_interval` seconds, per stack
+    pub tick_damage: i64,
+    pub tick_interval: f32,
+    // Fraction of speed removed by a slow
+    pub magnitude: f32,
+}
+
+struct ActiveStatus {
+    effect: StatusEffect,
+    remaining: f32,
+    tick_timer: f32,
+    stacks: u32,
+}
+
+// What happened during one `StatusEffects::tick`.
+#[derive(Debug, Default, PartialEq)]
+pub struct StatusTick {
+    pub damage: Vec<(StatusKind, i64)>,
+    pub expired: Vec<StatusKind>,
+}
+
+// Active statuses of one entity, with their stacking and immunity rules:
+// - poison stacks up to `MAX_POISON_STACKS` and refreshes its duration
+// - burn and stun refresh their duration, keeping the strongest tick
+// - slow keeps the strongest magnitude and refreshes its duration
+#[derive(Default)]
+pub struct StatusEffects {
+    active: Vec<ActiveStatus>,
+    immunities: Vec<StatusKind>,
+}
+
+impl StatusEffects {
+    pub fn set_immune(&mut self, kind: StatusKind, immune: bool) {
+        self.immunities.retain(|k| *k != kind);
+
+        if immune {
+            self.immunities.push(kind);
+            self.active.retain(|status| status.effect.kind != kind);
+        }
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.active.iter().any(|status| status.effect.kind == kind)
+    }
+
+    // Returns false if the entity is immune to `effect`.
+    pub fn apply(&mut self, effect: StatusEffect) -> bool {
+        if self.immunities.contains(&effect.kind) {
+            return false;
+        }
+
+        let existing = self
+            .active
+            .iter_mut()
+            .find(|status| status.effect.kind == effect.kind);
+
+        match existing {
+            Some(status) => {
+                status.remaining = status.remaining.max(effect.duration);
+                status.effect.duration = status.effect.duration.max(effect.duration);
+                status.effect.tick_damage = status.effect.tick_damage.max(effect.tick_damage);
+                status.effect.magnitude = status.effect.magnitude.max(effect.magnitude);
+
+                if effect.kind == StatusKind::Poison {
+                    status.stacks = (status.stacks + 1).min(MAX_POISON_STACKS);
+                }
+            }
+            None => self.active.push(ActiveStatus {
+                effect,
+                remaining: effect.duration,
+                tick_timer: 0.0,
+                stacks: 1,
+            }),
+        }
+
+        true
+    }
+
+    pub fn tick(&mut self, delta: f32) -> StatusTick {
+        let mut tick = StatusTick::default();
+
+        for status in self.active.iter_mut() {
+            // Ticks past the end of the status are not dealt
+            let elapsed = delta.min(status.remaining);
+            status.remaining -= delta;
+
+            if status.effect.tick_damage > 0 && status.effect.tick_interval > 0.0 {
+                status.tick_timer += elapsed;
+
+                while status.tick_timer >= status.effect.tick_interval {
+                    status.tick_timer -= status.effect.tick_interval;
+                    tick.damage.push((
+                        status.effect.kind,
+                        status.effect.tick_damage * status.stacks as i64,
+                    ));
+                }
+            }
+
+            if status.remaining <= 0.0 {
+                tick.expired.push(status.effect.kind);
+            }
+        }
+
+        self.active.retain(|status| status.remaining > 0.0);
+
+        tick
+    }
+
+    pub fn clear(&mut self) {
+        self.active.clear();
+    }
+
+    // Multiplier for movement speed: 0 while stunned, reduced by the strongest slow
+    pub fn speed_multiplier(&self) -> f32 {
+        if self.has(StatusKind::Stun) {
+            return 0.0;
+        }
+
+        let slow = self
+            .active
+            .iter()
+            .filter(|status| status.effect.kind == StatusKind::Slow)
+            .map(|status| status.effect.magnitude)
+            .fold(0.0, f32::max);
+
+        num::clamp(1.0 - slow, 0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effect(kind: StatusKind, duration: f32) -> StatusEffect {
+        StatusEffect {
+            kind,
+            duration,
+            tick_damage: 1,
+            tick_interval: 0.5,
+            magnitude: 0.0,
+        }
+    }
+
+    #[test]
+    fn poison_stacks_up_to_the_max() {
+        let mut statuses = StatusEffects::default();
+        for _ in 0..MAX_POISON_STACKS + 2 {
+            statuses.apply(effect(StatusKind::Poison, 2.0));
+        }
+
+        let tick = statuses.tick(0.5);
+        assert_eq!(
+            tick.damage,
+            vec![(StatusKind::Poison, MAX_POISON_STACKS as i64)]
+        );
+    }
+
+    #[test]
+    fn burn_does_not_stack() {
+        let mut statuses = StatusEffects::default();
+        statuses.apply(effect(StatusKind::Burn, 2.0));
+        statuses.apply(effect(StatusKind::Burn, 2.0));
+
+        assert_eq!(statuses.tick(0.5).damage, vec![(StatusKind::Burn, 1)]);
+    }
+
+    #[test]
+    fn reapplying_refreshes_instead_of_extending() {
+        let mut statuses = StatusEffects::default();
+        statuses.apply(effect(StatusKind::Slow, 2.0));
+        statuses.tick(1.5);
+
+        // Back to the full 2 seconds, not 0.5 + 2
+        statuses.apply(effect(StatusKind::Slow, 2.0));
+        assert!(statuses.tick(1.75).expired.is_empty());
+        assert_eq!(statuses.tick(0.5).expired, vec![StatusKind::Slow]);
+    }
+
+    #[test]
+    fn shorter_reapply_keeps_the_longer_duration() {
+        let mut statuses = StatusEffects::default();
+        statuses.apply(effect(StatusKind::Stun, 2.0));
+        statuses.apply(effect(StatusKind::Stun, 0.5));
+
+        assert!(statuses.tick(1.0).expired.is_empty());
+        assert!(statuses.has(StatusKind::Stun));
+    }
+
+    #[test]
+    fn immunity_rejects_effect() {
+        let mut statuses = StatusEffects::default();
+        statuses.apply(effect(StatusKind::Poison, 2.0));
+
+        statuses.set_immune(StatusKind::Poison, true);
+        assert!(!statuses.has(StatusKind::Poison));
+        assert!(!statuses.apply(effect(StatusKind::Poison, 2.0)));
+        assert!(!statuses.has(StatusKind::Poison));
+
+        statuses.set_immune(StatusKind::Poison, false);
+        assert!(statuses.apply(effect(StatusKind::Poison, 2.0)));
+    }
+
+    #[test]
+    fn damage_ticks_every_interval() {
+        let mut statuses = StatusEffects::default();
+        statuses.apply(effect(StatusKind::Poison, 3.0));
+
+        assert!(statuses.tick(0.25).damage.is_empty());
+        assert_eq!(statuses.tick(0.25).damage.len(), 1);
+        // A long frame deals every tick it covered
+        assert_eq!(statuses.tick(1.0).damage.len(), 2);
+    }
+
+    #[test]
+    fn expires_without_ticking_past_the_end() {
+        let mut statuses = StatusEffects::default();
+        statuses.apply(effect(StatusKind::Burn, 1.0));
+
+        let tick = statuses.tick(5.0);
+        assert_eq!(tick.damage.len(), 2);
+        assert_eq!(tick.expired, vec![StatusKind::Burn]);
+        assert!(!statuses.has(StatusKind::Burn));
+        assert_eq!(statuses.speed_multiplier(), 1.0);
+    }
+
+    #[test]
+    fn strongest_slow_and_stun_set_the_speed() {
+        let mut statuses = StatusEffects::default();
+        statuses.apply(StatusEffect {
+            magnitude: 0.25,
+            ..effect(StatusKind::Slow, 2.0)
+        });
+        statuses.apply(StatusEffect {
+            magnitude: 0.5,
+            ..effect(StatusKind::Slow, 1.0)
+        });
+        assert_eq!(statuses.speed_multiplier(), 0.5);
+
+        statuses.apply(effect(StatusKind::Stun, 1.0));
+        assert_eq!(statuses.speed_multiplier(), 0.0);
+    }
+}
@@ -1,3 +1,6 @@
+use std::fmt;
+
+use gdnative::api::{AudioStreamPlayer, File, NativeScript};
 use gdnative::prelude::*;
 
 #[inline]
@@ -16,3 +19,127 @@ pub fn load_scene(path: &str) -> Option<Ref<PackedScene, Shared>> {
     let scene = unsafe { scene.assume_unique().into_shared() };
     scene.cast::<PackedScene>()
 }
+
+// Contents of a text file, `None` if it is missing or can't be opened
+pub fn read_text_file(path: &str) -> Option<String> {
+    let file = File::new();
+
+    if !file.file_exists(path) {
+        return None;
+    }
+
+    if let Err(error) = file.open(path, File::READ) {
+        godot_print!("Could not open {}: {:?}", path, error);
+        return None;
+    }
+
+    let text = file.get_as_text().to_string();
+    file.close();
+
+    Some(text)
+}
+
+#[inline]
+// Stable id of a node, its path in the scene tree
+pub fn node_id(node: &Node) -> String {
+    node.get_path().to_godot_string().to_string()
+}
+
+// Plays a non-positional `AudioStreamPlayer`, e.g. a menu sound
+pub fn play_sound(sound: &Ref<AudioStreamPlayer>) {
+    unsafe { sound.assume_safe() }.play(0.0);
+}
+
+// Why `get_typed` could not return a node
+#[derive(Debug)]
+pub struct NodeError {
+    owner: String,
+    path: String,
+    expected: &'static str,
+    // Class of the node at `path`, `None` if there is none
+    found: Option<String>,
+}
+
+impl NodeError {
+    pub fn new(owner: &Node, path: &str, expected: &'static str, found: Option<String>) -> Self {
+        NodeError {
+            owner: class_name(owner),
+            path: path.to_string(),
+            expected,
+            found,
+        }
+    }
+}
+
+impl fmt::Display for NodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.found {
+            None => write!(
+                f,
+                "{}: no node at \"{}\" (expected {})",
+                self.owner, self.path, self.expected
+            ),
+            Some(found) => write!(
+                f,
+                "{}: node at \"{}\" is {} (expected {})",
+                self.owner, self.path, found, self.expected
+            ),
+        }
+    }
+}
+
+// Node at `path` from `owner` as a `T`. A missing or mistyped node is an error
+// naming both, rather than a panic taking the whole engine down.
+pub fn get_typed<'a, T>(owner: &'a Node, path: &str) -> Result<TRef<'a, T>, NodeError>
+where
+    T: GodotObject + SubClass<Node>,
+{
+    let error = |found| NodeError::new(owner, path, T::class_name(), found);
+
+    let node = owner.get_node_or_null(path).ok_or_else(|| error(None))?;
+    let node = unsafe { node.assume_safe() };
+
+    node.cast::<T>()
+        .ok_or_else(|| error(Some(node.get_class().to_string())))
+}
+
+// `get_typed` for the `Ref<T>` fields kept by the classes
+pub fn get_ref<T>(owner: &Node, path: &str) -> Result<Ref<T>, NodeError>
+where
+    T: GodotObject + SubClass<Node>,
+{
+    get_typed::<T>(owner, path).map(|node| node.claim())
+}
+
+// Script instance of the node at `path`, kept to call its Rust methods through
+// `map` instead of a dynamic `call` every frame
+pub fn get_instance<C>(owner: &Node, path: &str) -> Result<Instance<C, Shared>, NodeError>
+where
+    C: NativeClass,
+    C::Base: GodotObject + SubClass<Node>,
+{
+    let node = get_typed::<C::Base>(owner, path)?;
+
+    node.cast_instance::<C>()
+        .map(|instance| instance.claim())
+        .ok_or_else(|| {
+            let found = class_name(&node.upcast::<Node>());
+            NodeError::new(owner, path, C::class_name(), Some(found))
+        })
+}
+
+// Leaves `owner` in the tree but stops its processing after a failed lookup
+pub fn disable(owner: &Node, error: NodeError) {
+    godot_print!("{}, disabling it", error);
+
+    owner.set_process(false);
+    owner.set_physics_process(false);
+}
+
+// Name of the NativeScript class of `node`, its engine class without one
+fn class_name(node: &Node) -> String {
+    node.get_script()
+        .and_then(|script| unsafe { script.assume_safe() }.cast::<NativeScript>())
+        .map(|script| script.class_name().to_string())
+        .unwrap_or_else(|| node.get_class().to_string())
+}
@@ -0,0 +1,85 @@
+// Health and stamina bookkeeping of `Stats`, kept free of Godot so it can be tested.
+
+// Health set to `value` within `0..=max_health`, and whether this change ran it
+// out. Already at 0 it can't run out again, so `no_health` is sent once.
+pub fn change_health(health: i64, max_health: i64, value: i64) -> (i64, bool) {
+    let changed = num::clamp(value, 0, max_health.max(0));
+
+    (changed, health > 0 && changed == 0)
+}
+
+// Stamina left once `cost` is paid, None without enough of it
+pub fn spend_stamina(stamina: f64, cost: f64) -> Option<f64> {
+    if stamina < cost {
+        return None;
+    }
+
+    Some(stamina - cost)
+}
+
+// Stamina after `delta` seconds of regaining `regen` per second, up to `max`
+pub fn regen_stamina(stamina: f64, max_stamina: f64, regen: f64, delta: f64) -> f64 {
+    (stamina + regen.max(0.0) * delta).min(max_stamina)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_killing_blow_runs_health_out() {
+        assert_eq!(change_health(2, 5, -3), (0, true));
+    }
+
+    #[test]
+    fn a_hit_that_leaves_health_does_not() {
+        assert_eq!(change_health(5, 5, 3), (3, false));
+    }
+
+    #[test]
+    fn hitting_the_dead_does_not_run_health_out_again() {
+        assert_eq!(change_health(0, 5, -1), (0, false));
+        assert_eq!(change_health(0, 5, 0), (0, false));
+    }
+
+    #[test]
+    fn healing_is_capped_at_max_health() {
+        assert_eq!(change_health(3, 5, 9), (5, false));
+    }
+
+    #[test]
+    fn spending_takes_the_cost() {
+        assert_eq!(spend_stamina(100.0, 25.0), Some(75.0));
+    }
+
+    #[test]
+    fn the_last_of_the_stamina_can_be_spent() {
+        assert_eq!(spend_stamina(25.0, 25.0), Some(0.0));
+    }
+
+    #[test]
+    fn spending_more_than_there_is_fails() {
+        assert_eq!(spend_stamina(20.0, 25.0), None);
+    }
+
+    #[test]
+    fn regen_is_per_second() {
+        assert_eq!(regen_stamina(50.0, 100.0, 25.0, 0.5), 62.5);
+    }
+
+    #[test]
+    fn regen_stops_at_the_max() {
+        assert_eq!(regen_stamina(95.0, 100.0, 25.0, 1.0), 100.0);
+    }
+
+    #[test]
+    fn regen_never_takes_stamina_away() {
+        assert_eq!(regen_stamina(50.0, 100.0, 0.0, 1.0), 50.0);
+        assert_eq!(regen_stamina(50.0, 100.0, -10.0, 1.0), 50.0);
+    }
+
+    #[test]
+    fn stamina_above_a_lowered_max_is_capped() {
+        assert_eq!(regen_stamina(120.0, 100.0, 25.0, 1.0), 100.0);
+    }
+}
@@ -0,0 +1,90 @@
+use rand::Rng;
+
+// Entries picked with a probability proportional to their weight.
+pub struct WeightedTable<T> {
+    entries: Vec<(T, u32)>,
+    total_weight: u32,
+}
+
+impl<T> Default for WeightedTable<T> {
+    fn default() -> Self {
+        WeightedTable {
+            entries: Vec::new(),
+            total_weight: 0,
+        }
+    }
+}
+
+impl<T> WeightedTable<T> {
+    // Entries with a weight of 0 are never picked and are not added
+    pub fn add(&mut self, item: T, weight: u32) {
+        if weight == 0 {
+            return;
+        }
+
+        self.entries.push((item, weight));
+        self.total_weight += weight;
+    }
+
+    pub fn pick<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<&T> {
+        if self.total_weight == 0 {
+            return None;
+        }
+
+        let mut roll = rng.gen_range(0..self.total_weight);
+
+        for (item, weight) in self.entries.iter() {
+            if roll < *weight {
+                return Some(item);
+            }
+            roll -= weight;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+
+    #[test]
+    fn empty_table_picks_nothing() {
+        let mut table = WeightedTable::default();
+        table.add("bat", 0);
+
+        assert_eq!(table.pick(&mut Pcg64::seed_from_u64(1)), None);
+    }
+
+    #[test]
+    fn picks_in_proportion_to_weight() {
+        let mut table = WeightedTable::default();
+        table.add("bat", 3);
+        table.add("never", 0);
+        table.add("slime", 1);
+
+        let mut rng = Pcg64::seed_from_u64(1);
+        let bats = (0..4000)
+            .filter(|_| table.pick(&mut rng) == Some(&"bat"))
+            .count();
+
+        // 3000 expected
+        assert!((2850..3150).contains(&bats), "{} bats", bats);
+    }
+
+    #[test]
+    fn same_seed_same_picks() {
+        let mut table = WeightedTable::default();
+        table.add(1, 1);
+        table.add(2, 1);
+        table.add(3, 1);
+
+        let mut a = Pcg64::seed_from_u64(9);
+        let mut b = Pcg64::seed_from_u64(9);
+        for _ in 0..100 {
+            assert_eq!(table.pick(&mut a), table.pick(&mut b));
+        }
+    }
+}